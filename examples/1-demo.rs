@@ -10,16 +10,17 @@ fn print_hex_buffer(header: &str, v: &Vec<u8>) {
     for x in v {
         print!("{:02X} ", x);
     }
-    println!("");
+    println!();
 }
 
 /// Simple wake_rs API demo
 fn main() {
-    let wp = wake_rs::Packet {
-        address: Some(0x12),
-        command: 3,
-        data: Some(vec![0x00, 0xeb]),
-    };
+    let wp = wake_rs::Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, 0xeb])
+        .build()
+        .unwrap();
 
     let encoded = wp.encode().unwrap();
     print_hex_buffer("Encoded packet:\t", &encoded);
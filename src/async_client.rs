@@ -0,0 +1,449 @@
+//! Async request/response client on tokio
+
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tokio::time::{self, Instant};
+
+use crate::client::match_reply;
+use crate::frame::encode_to_vec_with;
+use crate::std_commands::{self, ECHO, INFO};
+use crate::{Checksum, ClientError, Config, Crc8, Packet, RetryPolicy, StreamDecoder};
+
+/// Default reply timeout of [`AsyncWakeClient`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Receive buffer length of [`AsyncWakeClient`]
+const RECV_BUF_LEN: usize = 64;
+
+/// Port state, owned by one request at a time
+#[derive(Debug)]
+struct Port<S, C> {
+    stream: S,
+    decoder: StreamDecoder<C>,
+}
+
+/// Async client: sends a request and waits for the matching reply
+///
+/// Requests are matched to replies like [`WakeClient`](crate::WakeClient) does. The client
+/// might be shared between tasks (e.g. in an `Arc`), requests to the port are sent one at a
+/// time. A request is cancelled by dropping its future, the next one resynchronizes on the
+/// next frame.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use futures::{SinkExt, StreamExt};
+/// use tokio_util::codec::Framed;
+/// use wake_rs::{AsyncWakeClient, WakeCodec};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (host, device) = tokio::io::duplex(64);
+/// // echo device
+/// tokio::spawn(async move {
+///     let mut device = Framed::new(device, WakeCodec::default());
+///     while let Some(Ok(p)) = device.next().await {
+///         device.send(p).await.unwrap();
+///     }
+/// });
+/// let client = AsyncWakeClient::new(host);
+/// let reply = client.request(Some(0x12), 0x10, &[2, 1]).await.unwrap();
+/// assert_eq!(reply.data(), Some(&[2, 1][..]));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncWakeClient<S, C = Crc8> {
+    port: Mutex<Port<S, C>>,
+    config: Config<C>,
+    timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWakeClient<S> {
+    /// Create a CRC-8 client, 100 ms reply timeout, no retries
+    pub fn new(stream: S) -> Self {
+        Self::with_config(stream, Config::new())
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin, C: Checksum> AsyncWakeClient<S, C> {
+    /// Create a client with the given link configuration
+    pub fn with_config(stream: S, config: Config<C>) -> Self {
+        AsyncWakeClient {
+            port: Mutex::new(Port {
+                stream,
+                decoder: StreamDecoder::with_config(config),
+            }),
+            config,
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::none(),
+        }
+    }
+
+    /// Set the default reply timeout, per attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send a request and wait for the reply with the default timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address (optional) [0 - 127]
+    /// * `command` - command [0 - 127]
+    /// * `payload` - request data, might be empty
+    ///
+    /// # Output
+    ///
+    /// * `Result<Packet, ClientError>` - reply with the same command (and address) or the
+    ///   error of the last attempt: `Timeout`, or the last broken frame (`Protocol`) or
+    ///   mismatched reply received before the deadline. Waiting for the port counts toward
+    ///   the timeout, a request still queued when it passes fails with `Timeout` unsent.
+    ///
+    pub async fn request(
+        &self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+    ) -> Result<Packet, ClientError> {
+        self.request_until(address, command, payload, self.timeout, None)
+            .await
+    }
+
+    /// Send a request and wait for the reply, `timeout` is applied to every attempt
+    pub async fn request_with_timeout(
+        &self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Result<Packet, ClientError> {
+        self.request_until(address, command, payload, timeout, None)
+            .await
+    }
+
+    /// Send a request and wait for the reply until the deadline, retries included
+    ///
+    /// No retry is started after the deadline and the last attempt is cut short by it. A request
+    /// still waiting for the port at the deadline fails with `Timeout` and is never sent.
+    pub async fn request_with_deadline(
+        &self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+        deadline: Instant,
+    ) -> Result<Packet, ClientError> {
+        self.request_until(address, command, payload, self.timeout, Some(deadline))
+            .await
+    }
+
+    async fn request_until(
+        &self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+        timeout: Duration,
+        deadline: Option<Instant>,
+    ) -> Result<Packet, ClientError> {
+        let mut tx = Vec::new();
+        encode_to_vec_with(address, command, payload, &mut tx, &self.config)?;
+        // a request queued behind slow ones is dropped unsent once its time is up
+        let wait_deadline = deadline.unwrap_or_else(|| Instant::now() + timeout);
+        let mut port = time::timeout_at(wait_deadline, self.port.lock())
+            .await
+            .map_err(|_| ClientError::Timeout)?;
+        let mut retry = 0;
+        loop {
+            let mut attempt_deadline = Instant::now() + timeout;
+            if let Some(deadline) = deadline {
+                attempt_deadline = attempt_deadline.min(deadline);
+            }
+            let mut skipped = None;
+            let attempt = port.attempt(&tx, address, command, &mut skipped);
+            let result = match time::timeout_at(attempt_deadline, attempt).await {
+                Ok(result) => result,
+                Err(_) => Err(skipped.unwrap_or(ClientError::Timeout)),
+            };
+            match result {
+                Err(e) if e.is_retryable() && retry < self.retry.retries => {
+                    let next = Instant::now() + self.retry.delay(retry);
+                    if deadline.is_some_and(|deadline| next >= deadline) {
+                        return Err(e);
+                    }
+                    time::sleep_until(next).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Check the link: send `ECHO` and compare the reply data
+    ///
+    /// See [`WakeClient::ping`](crate::WakeClient::ping) for the arguments and errors.
+    pub async fn ping(&self, address: Option<u8>, payload: &[u8]) -> Result<(), ClientError> {
+        let reply = self.request(address, ECHO, payload).await?;
+        std_commands::check_echo(payload, &reply)
+    }
+
+    /// Device description replied to `INFO`, see [`WakeClient::info`](crate::WakeClient::info)
+    pub async fn info(&self, address: Option<u8>) -> Result<String, ClientError> {
+        let reply = self.request(address, INFO, &[]).await?;
+        std_commands::info_text(&reply)
+    }
+
+    /// Unwrap the stream
+    pub fn into_inner(self) -> S {
+        self.port.into_inner().stream
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin, C: Checksum> Port<S, C> {
+    /// Send the encoded request once and wait for the reply, broken frames and mismatched
+    /// replies are skipped and recorded
+    async fn attempt(
+        &mut self,
+        tx: &[u8],
+        address: Option<u8>,
+        command: u8,
+        skipped: &mut Option<ClientError>,
+    ) -> Result<Packet, ClientError> {
+        // 1: Send the request, a partial frame of the previous one is dropped
+        self.decoder.reset();
+        self.stream.write_all(tx).await?;
+        self.stream.flush().await?;
+        // 2: Wait for the reply, skip broken frames and other packets
+        let mut buf = [0u8; RECV_BUF_LEN];
+        loop {
+            let n = match self.stream.read(&mut buf).await {
+                Ok(0) => return Err(ClientError::Io(ErrorKind::UnexpectedEof)),
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for decoded in self.decoder.feed(&buf[..n]) {
+                let p = match decoded {
+                    Ok(p) => p,
+                    Err(e) => {
+                        *skipped = Some(e.into());
+                        continue;
+                    }
+                };
+                let other = ClientError::Mismatch {
+                    address: p.address.map(u8::from),
+                    command: p.command.get(),
+                };
+                if let Some(result) = match_reply(address, command, p) {
+                    return result;
+                }
+                *skipped = Some(other);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WakeCodec;
+    use futures::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio_util::codec::Framed;
+
+    /// Device on the other end of an in-memory link, replies with `reply(request number, request)`
+    fn spawn_device<F>(mut reply: F) -> tokio::io::DuplexStream
+    where
+        F: FnMut(usize, Packet) -> Vec<Packet> + Send + 'static,
+    {
+        let (host, device) = tokio::io::duplex(64);
+        tokio::spawn(async move {
+            let mut device = Framed::new(device, WakeCodec::default());
+            let mut i = 0;
+            while let Some(Ok(p)) = device.next().await {
+                for r in reply(i, p) {
+                    device.send(r).await.unwrap();
+                }
+                i += 1;
+            }
+        });
+        host
+    }
+
+    #[tokio::test]
+    async fn async_client_test() {
+        let host = spawn_device(|_, p| {
+            let late = Packet::builder().command(1).build().unwrap();
+            vec![late, p]
+        });
+        let client = Arc::new(AsyncWakeClient::new(host));
+        let reply = client.request(Some(0x12), 0x10, &[2, 1]).await.unwrap();
+        assert_eq!(reply.address.unwrap(), 0x12);
+        assert_eq!(reply.data(), Some(&[2, 1][..]));
+        // concurrent requests are sent one at a time
+        let tasks: Vec<_> = (2..10u8)
+            .map(|command| {
+                let client = client.clone();
+                tokio::spawn(async move { client.request(None, command, &[command]).await })
+            })
+            .collect();
+        for (task, command) in tasks.into_iter().zip(2..10u8) {
+            let reply = task.await.unwrap().unwrap();
+            assert_eq!(reply.command, command);
+            assert_eq!(reply.data(), Some(&[command][..]));
+        }
+        assert_eq!(
+            client.request(None, 1, &[0; 300]).await,
+            Err(ClientError::Protocol(crate::WakeError::TooLongData {
+                len: 300,
+                max: 255
+            }))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn async_client_retry_test() {
+        // no reply to the first two requests, a reply of another command to the third one
+        let flaky = |i, p: Packet| match i {
+            0 | 1 => vec![],
+            2 => vec![Packet::builder().command(1).build().unwrap()],
+            _ => vec![p],
+        };
+        let client = AsyncWakeClient::new(spawn_device(flaky))
+            .with_timeout(Duration::from_millis(20))
+            .with_retry(RetryPolicy::new(3, Duration::from_millis(1)));
+        let started = Instant::now();
+        assert_eq!(client.request(None, 5, &[]).await.unwrap().command, 5);
+        // three timed out attempts and 1 + 2 + 4 ms of backoff
+        assert_eq!(started.elapsed(), Duration::from_millis(3 * 20 + 7));
+
+        let client = AsyncWakeClient::new(spawn_device(flaky))
+            .with_timeout(Duration::from_millis(20))
+            .with_retry(RetryPolicy::new(2, Duration::ZERO));
+        assert_eq!(
+            client.request(None, 5, &[]).await,
+            Err(ClientError::Mismatch {
+                address: None,
+                command: 1
+            })
+        );
+        // the deadline stops retries
+        let client = AsyncWakeClient::new(spawn_device(|_, _| vec![]))
+            .with_retry(RetryPolicy::new(100, Duration::from_millis(1)));
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(50);
+        let result = client.request_with_deadline(None, 5, &[], deadline).await;
+        assert_eq!(result, Err(ClientError::Timeout));
+        assert_eq!(started.elapsed(), Duration::from_millis(50));
+        let client = AsyncWakeClient::new(client.into_inner());
+        let started = Instant::now();
+        let result = client
+            .request_with_timeout(None, 5, &[], Duration::from_millis(1))
+            .await;
+        assert_eq!(result, Err(ClientError::Timeout));
+        assert_eq!(started.elapsed(), Duration::from_millis(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn async_client_queue_timeout_test() {
+        // a request queued behind a slow one times out without being sent
+        let (host, device) = tokio::io::duplex(64);
+        let client = Arc::new(AsyncWakeClient::new(host));
+        let slow = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .request_with_timeout(None, 1, &[], Duration::from_millis(50))
+                    .await
+            }
+        });
+        tokio::task::yield_now().await;
+        let started = Instant::now();
+        let queued = client
+            .request_with_timeout(None, 2, &[], Duration::from_millis(10))
+            .await;
+        assert_eq!(queued, Err(ClientError::Timeout));
+        assert_eq!(started.elapsed(), Duration::from_millis(10));
+        let deadline = Instant::now() + Duration::from_millis(5);
+        let queued = client.request_with_deadline(None, 3, &[], deadline).await;
+        assert_eq!(queued, Err(ClientError::Timeout));
+        assert_eq!(slow.await.unwrap(), Err(ClientError::Timeout));
+        assert_eq!(started.elapsed(), Duration::from_millis(50));
+        // only the first request reached the device
+        let mut device = Framed::new(device, WakeCodec::default());
+        assert_eq!(device.next().await.unwrap().unwrap().command, 1);
+        let more = time::timeout(Duration::from_millis(100), device.next()).await;
+        assert!(more.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn async_client_noise_test() {
+        use crate::Encode;
+        // line noise breaks a frame before the reply, the request is not repeated
+        let (host, mut device) = tokio::io::duplex(64);
+        let client = AsyncWakeClient::new(host).with_retry(RetryPolicy::new(3, Duration::ZERO));
+        let reply = Packet::builder().command(5).payload([1]).build().unwrap();
+        let mut rx = vec![crate::FEND, 0x05, 0x01, 0x00];
+        rx.extend(reply.encode().unwrap());
+        let request = client.request(None, 5, &[1]);
+        let device = async {
+            let mut frame = [0u8; 8];
+            let n = device.read(&mut frame).await.unwrap();
+            device.write_all(&rx).await.unwrap();
+            // nothing else is sent
+            let more = time::timeout(Duration::from_millis(200), device.read(&mut frame)).await;
+            (n, more.is_err())
+        };
+        let (result, (n, quiet)) = tokio::join!(request, device);
+        assert_eq!(result, Ok(reply));
+        assert!(n > 0 && quiet);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn async_client_cancel_test() {
+        // no reply to the first request, the late one comes with the second reply
+        let host = spawn_device(|i, p| match i {
+            0 => vec![],
+            _ => vec![Packet::builder().command(3).build().unwrap(), p],
+        });
+        let client = AsyncWakeClient::new(host).with_timeout(Duration::from_secs(1));
+        let cancelled =
+            time::timeout(Duration::from_millis(10), client.request(None, 3, &[])).await;
+        assert!(cancelled.is_err());
+        // the late reply to command 3 is skipped
+        assert_eq!(client.request(None, 4, &[]).await.unwrap().command, 4);
+        // the other end is closed
+        drop(client.into_inner());
+        let (host, device) = tokio::io::duplex(64);
+        drop(device);
+        let client = AsyncWakeClient::new(host);
+        assert_eq!(
+            client.request(None, 1, &[]).await,
+            Err(ClientError::Io(ErrorKind::BrokenPipe))
+        );
+    }
+
+    #[tokio::test]
+    async fn async_client_std_commands_test() {
+        let mut server = crate::WakeServer::new();
+        server.register_std_commands("Relay shield v1.0");
+        let host = spawn_device(move |_, p| server.handle(&p).into_iter().collect());
+        let client = AsyncWakeClient::new(host);
+        assert_eq!(client.ping(Some(0x12), &[1, 2, 3]).await, Ok(()));
+        assert_eq!(client.info(None).await, Ok("Relay shield v1.0".to_string()));
+        assert_eq!(
+            client.request(None, 0x7f, &[]).await,
+            Err(ClientError::Device(crate::ErrorCode::PARAMETER))
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Packet stream and sink over `futures::io`, for smol, async-std and other runtimes
+
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use crate::{Checksum, Config, Crc8, Packet, PacketRef, StreamDecoder, StreamError};
+
+/// Read buffer length of [`PacketStream`]
+const READ_BUF_LEN: usize = 64;
+
+/// Stream of packets read from an `AsyncRead`
+///
+/// Broken frames are reported as errors and the stream goes on with the next frame.
+/// The stream ends when the reader reaches EOF.
+///
+/// Dropping a pending `next()` is safe: bytes already read are kept in the stream.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use futures::StreamExt;
+/// use wake_rs::PacketStream;
+///
+/// # futures::executor::block_on(async {
+/// let rx: &[u8] = &[0xC0, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6b];
+/// let mut packets = PacketStream::new(rx);
+/// while let Some(decoded) = packets.next().await {
+///     println!("Decoded packet\t: {}", decoded.unwrap());
+/// }
+/// # });
+/// ```
+pub struct PacketStream<R, C = Crc8> {
+    reader: R,
+    decoder: StreamDecoder<C>,
+    buf: [u8; READ_BUF_LEN],
+    pos: usize,
+    len: usize,
+}
+
+impl<R: AsyncRead + Unpin> PacketStream<R> {
+    /// Create a CRC-8 packet stream
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, Config::new())
+    }
+}
+
+impl<R: AsyncRead + Unpin, C: Checksum> PacketStream<R, C> {
+    /// Create a packet stream with the given link configuration
+    pub fn with_config(reader: R, config: Config<C>) -> Self {
+        PacketStream {
+            reader,
+            decoder: StreamDecoder::with_config(config),
+            buf: [0; READ_BUF_LEN],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Underlying reader, reading from it directly may break the current frame
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the reader, buffered bytes and the current frame are lost
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin, C: Checksum + Unpin> Stream for PacketStream<R, C> {
+    type Item = Result<Packet, StreamError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // 1: Decode buffered bytes
+            while this.pos < this.len {
+                let byte = this.buf[this.pos];
+                this.pos += 1;
+                if let Some(decoded) = this.decoder.push(byte) {
+                    return Poll::Ready(Some(decoded.map_err(StreamError::Wake)));
+                }
+            }
+            // 2: Read more bytes
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut this.buf)) {
+                Ok(0) => return Poll::Ready(None),
+                Ok(n) => {
+                    this.pos = 0;
+                    this.len = n;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+        }
+    }
+}
+
+/// Sink of packets written to an `AsyncWrite`
+///
+/// Every packet is encoded into an internal buffer, which is written out before the next
+/// packet is accepted and on flush. Dropping a pending `send()` is safe: an accepted packet
+/// stays in the buffer.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use futures::SinkExt;
+/// use wake_rs::{Packet, PacketSink};
+///
+/// # futures::executor::block_on(async {
+/// let mut sink = PacketSink::new(Vec::new());
+/// sink.send(Packet::builder().command(3).build().unwrap()).await.unwrap();
+/// assert_eq!(sink.get_ref(), &vec![0xC0, 0x03, 0x00, 0xeb]);
+/// # });
+/// ```
+pub struct PacketSink<W, C = Crc8> {
+    writer: W,
+    config: Config<C>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<W: AsyncWrite + Unpin> PacketSink<W> {
+    /// Create a CRC-8 packet sink
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::new())
+    }
+}
+
+impl<W: AsyncWrite + Unpin, C: Checksum> PacketSink<W, C> {
+    /// Create a packet sink with the given link configuration
+    pub fn with_config(writer: W, config: Config<C>) -> Self {
+        PacketSink {
+            writer,
+            config,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Underlying writer, writing to it directly may break the current frame
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwrap the writer, buffered bytes are lost
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write out the buffer
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        while self.pos < self.buf.len() {
+            match ready!(Pin::new(&mut self.writer).poll_write(cx, &self.buf[self.pos..])) {
+                Ok(0) => {
+                    return Poll::Ready(Err(
+                        std::io::Error::from(std::io::ErrorKind::WriteZero).into()
+                    ))
+                }
+                Ok(n) => self.pos += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Poll::Ready(Err(e.into())),
+            }
+        }
+        self.buf.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<'a, W: AsyncWrite + Unpin, C: Checksum + Unpin> Sink<PacketRef<'a>> for PacketSink<W, C> {
+    type Error = StreamError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: PacketRef<'a>) -> Result<(), StreamError> {
+        let this = self.get_mut();
+        crate::frame::encode_to_vec_with(
+            item.address,
+            item.command,
+            item.data,
+            &mut this.buf,
+            &this.config,
+        )?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.writer).poll_flush(cx)).map_err(StreamError::from))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.writer).poll_close(cx)).map_err(StreamError::from))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, C: Checksum + Unpin> Sink<Packet> for PacketSink<W, C> {
+    type Error = StreamError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        Sink::<PacketRef>::poll_ready(self, cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<(), StreamError> {
+        Sink::<PacketRef>::start_send(self, PacketRef::from(&item))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        Sink::<PacketRef>::poll_flush(self, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), StreamError>> {
+        Sink::<PacketRef>::poll_close(self, cx)
+    }
+}
+
+/// Reader which returns `Pending` before every chunk
+#[cfg(test)]
+struct ChunkReader {
+    chunks: Vec<Vec<u8>>,
+    ready: bool,
+}
+
+#[cfg(test)]
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        if self.chunks.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let chunk = self.chunks.remove(0);
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        Poll::Ready(Ok(chunk.len()))
+    }
+}
+
+#[cfg(test)]
+use crate::{Encode, WakeError, FEND};
+#[cfg(test)]
+use futures::{executor::block_on, task::noop_waker, FutureExt, SinkExt, StreamExt};
+
+#[test]
+fn packet_stream_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, FEND, 0xeb])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut rx = vec![0x00, FEND, 0x03, 0x01, 0xdb, 0x00]; // noise and a broken frame
+    rx.extend(&encoded);
+    rx.extend(&encoded);
+    let decoded: Vec<_> = block_on(PacketStream::new(&rx[..]).collect());
+    assert_eq!(decoded.len(), 3);
+    assert!(matches!(
+        decoded[0],
+        Err(StreamError::Wake(WakeError::DestuffingFailed {
+            offset: 4,
+            byte: 0x00
+        }))
+    ));
+    assert_eq!(decoded[1].as_ref().unwrap().data, wp.data);
+    assert_eq!(decoded[2].as_ref().unwrap().command, wp.command);
+}
+
+#[test]
+fn packet_stream_cancel_test() {
+    let wp = Packet::builder()
+        .command(1)
+        .payload(vec![1, 2, 3])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let reader = ChunkReader {
+        chunks: vec![encoded[..3].to_vec(), encoded[3..].to_vec()],
+        ready: false,
+    };
+    let mut packets = PacketStream::new(reader);
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    // every read is pending first, drop the future after the first chunk
+    for _ in 0..2 {
+        assert!(packets.next().poll_unpin(&mut cx).is_pending());
+    }
+    let decoded = block_on(packets.next()).unwrap().unwrap();
+    assert_eq!(decoded.data, wp.data);
+    assert!(block_on(packets.next()).is_none());
+}
+
+#[test]
+fn packet_sink_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![FEND])
+        .build()
+        .unwrap();
+    let mut sink = PacketSink::new(Vec::new());
+    block_on(sink.send(PacketRef::from(&wp))).unwrap();
+    block_on(sink.send(wp)).unwrap();
+    let wrong = PacketRef {
+        command: 0x80,
+        ..Default::default()
+    };
+    assert!(matches!(
+        block_on(sink.send(wrong)),
+        Err(StreamError::Wake(WakeError::WrongCmdRange {
+            command: 0x80
+        }))
+    ));
+    let tx = sink.into_inner();
+    let packets: Vec<_> = block_on(PacketStream::new(&tx[..]).collect());
+    assert_eq!(packets.len(), 2);
+    assert!(packets
+        .iter()
+        .all(|p| p.as_ref().unwrap().data() == Some(&[FEND][..])));
+}
@@ -0,0 +1,107 @@
+//! Validating builder of owned packets
+
+use crate::{Address, Command, Packet, PacketRef, WakeError, DATA_MAX_LEN};
+
+/// Builder of a [`Packet`], see [`Packet::builder`]
+///
+/// Values are checked by [`PacketBuilder::build`], so a built packet is always valid.
+#[derive(Clone, Debug, Default)]
+pub struct PacketBuilder {
+    address: Option<u8>,
+    command: u8,
+    data: Vec<u8>,
+}
+
+impl PacketBuilder {
+    /// Device address [0 - 127], the packet has no address by default
+    pub fn address(mut self, address: u8) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Command [0 - 127], 0 by default
+    pub fn command(mut self, command: u8) -> Self {
+        self.command = command;
+        self
+    }
+
+    /// Data load [0 - `DATA_MAX_LEN`] bytes, empty by default
+    pub fn payload(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// Check the values and build the packet
+    ///
+    /// # Output
+    ///
+    /// * `Result<Packet, WakeError>` - packet or `WrongAddrRange`, `WrongCmdRange`, `TooLongData`.
+    ///   An empty data load is stored as an empty vector.
+    ///
+    pub fn build(self) -> Result<Packet, WakeError> {
+        let address = self.address.map(Address::new).transpose()?;
+        let command = Command::new(self.command)?;
+        if self.data.len() > DATA_MAX_LEN {
+            return Err(WakeError::TooLongData {
+                len: self.data.len(),
+                max: DATA_MAX_LEN,
+            });
+        }
+        Ok(Packet {
+            address,
+            command,
+            data: self.data,
+        })
+    }
+}
+
+/// Copy a borrowed packet, its values are checked like [`PacketBuilder::build`] does
+impl<'a> TryFrom<PacketRef<'a>> for Packet {
+    type Error = WakeError;
+
+    fn try_from(p: PacketRef<'a>) -> Result<Self, Self::Error> {
+        PacketBuilder {
+            address: p.address,
+            command: p.command,
+            data: p.data.to_vec(),
+        }
+        .build()
+    }
+}
+
+#[test]
+fn packet_builder_test() {
+    let p = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload([0x00, 0xeb])
+        .build()
+        .unwrap();
+    assert_eq!(
+        p,
+        Packet {
+            address: Some(Address::new(0x12).unwrap()),
+            command: Command::new(3).unwrap(),
+            data: vec![0x00, 0xeb],
+        }
+    );
+    let empty = Packet::builder().command(4).payload(vec![]).build();
+    assert_eq!(empty, Ok(Packet::builder().command(4).build().unwrap()));
+    assert_eq!(empty.unwrap().data(), None);
+
+    assert_eq!(
+        Packet::builder().address(0x80).build(),
+        Err(WakeError::WrongAddrRange { address: 0x80 })
+    );
+    assert_eq!(
+        Packet::builder().command(0x80).build(),
+        Err(WakeError::WrongCmdRange { command: 0x80 })
+    );
+    assert_eq!(
+        Packet::builder().payload([0; DATA_MAX_LEN + 1]).build(),
+        Err(WakeError::TooLongData {
+            len: DATA_MAX_LEN + 1,
+            max: DATA_MAX_LEN
+        })
+    );
+}
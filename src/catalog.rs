@@ -0,0 +1,237 @@
+//! Command names and payload decoders shown by `Display` and `PacketFormatter`
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Payload decoder, `None` if the payload can't be decoded
+type Decoder = Box<dyn Fn(&[u8]) -> Option<String> + Send + Sync>;
+
+struct Entry {
+    name: String,
+    decoder: Option<Decoder>,
+}
+
+/// Catalog installed by [`CommandCatalog::set_global`]
+static GLOBAL: RwLock<Option<Arc<CommandCatalog>>> = RwLock::new(None);
+
+/// Application command names and payload decoders
+///
+/// A name is registered per address and command, `None` address matches packets with any
+/// (or no) address. Once the catalog is installed with [`CommandCatalog::set_global`], packets
+/// are shown as `CMD:  0x10 SET_RELAY relay=2 mode=1`: the decoded payload replaces the hex dump.
+///
+/// The installed catalog is process-wide state: it is shared by every crate that prints wake
+/// packets, so a library should pass its catalog to
+/// [`PacketFormatter::display_with_catalog`](crate::PacketFormatter::display_with_catalog)
+/// and leave installing one to the application. Every printed packet takes a read lock on it,
+/// use `with_names(false)` or an explicit catalog where that matters.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{CommandCatalog, PacketFormatter, PacketRef};
+///
+/// let mut catalog = CommandCatalog::new();
+/// catalog.register(None, 0x02, "GET_INFO");
+/// catalog.register_with_decoder(Some(0x12), 0x10, "SET_RELAY", |data| match data {
+///     [relay, mode] => Some(format!("relay={} mode={}", relay, mode)),
+///     _ => None,
+/// });
+/// CommandCatalog::set_global(catalog);
+///
+/// let p = PacketRef { address: Some(0x12), command: 0x10, data: &[2, 1] };
+/// let log = PacketFormatter::new().with_compact(true);
+/// assert_eq!(log.display(p).to_string(), "ADDR: 0x12 CMD: 0x10 SET_RELAY relay=2 mode=1");
+/// let p = PacketRef { address: None, command: 0x02, data: &[] };
+/// assert_eq!(log.display(p).to_string(), "ADDR: ---- CMD: 0x02 GET_INFO DATA: none");
+/// # CommandCatalog::clear_global();
+/// ```
+#[derive(Default)]
+pub struct CommandCatalog {
+    entries: HashMap<(Option<u8>, u8), Entry>,
+}
+
+impl CommandCatalog {
+    /// Create an empty catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a command name, a registered name and decoder are replaced
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address, `None` for any device
+    /// * `command` - command
+    /// * `name` - command name, e.g. `SET_RELAY`
+    ///
+    pub fn register(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        name: impl Into<String>,
+    ) -> &mut Self {
+        let entry = Entry {
+            name: name.into(),
+            decoder: None,
+        };
+        self.entries.insert((address, command), entry);
+        self
+    }
+
+    /// Register a command name and a payload decoder
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address, `None` for any device
+    /// * `command` - command
+    /// * `name` - command name, e.g. `SET_RELAY`
+    /// * `decoder` - payload decoder, e.g. `relay=2 mode=1` for `[02 01]`. The hex dump is
+    ///   shown when it returns `None`.
+    ///
+    pub fn register_with_decoder(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        name: impl Into<String>,
+        decoder: impl Fn(&[u8]) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let entry = Entry {
+            name: name.into(),
+            decoder: Some(Box::new(decoder)),
+        };
+        self.entries.insert((address, command), entry);
+        self
+    }
+
+    /// Find the entry of the device address first, then the one for any device
+    fn entry(&self, address: Option<u8>, command: u8) -> Option<&Entry> {
+        address
+            .and_then(|a| self.entries.get(&(Some(a), command)))
+            .or_else(|| self.entries.get(&(None, command)))
+    }
+
+    /// Command name
+    pub fn name(&self, address: Option<u8>, command: u8) -> Option<&str> {
+        self.entry(address, command).map(|e| e.name.as_str())
+    }
+
+    /// Decoded payload, `None` if there is no decoder or it can't decode `data`
+    pub fn decode(&self, address: Option<u8>, command: u8, data: &[u8]) -> Option<String> {
+        self.entry(address, command)?.decoder.as_ref()?(data)
+    }
+
+    /// Write ` NAME` and ` decoded payload` after the command
+    ///
+    /// # Output
+    ///
+    /// * `Result<bool, fmt::Error>` - true if the payload is decoded, the hex dump is not needed
+    ///
+    pub(crate) fn write_name(
+        &self,
+        f: &mut fmt::Formatter,
+        address: Option<u8>,
+        command: u8,
+        data: &[u8],
+    ) -> Result<bool, fmt::Error> {
+        let entry = match self.entry(address, command) {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        write!(f, " {}", entry.name)?;
+        match entry.decoder.as_ref().and_then(|decoder| decoder(data)) {
+            Some(decoded) => {
+                write!(f, " {}", decoded)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Install the catalog used by `Display` of all packet types, the previous one is replaced
+    ///
+    /// The catalog is global to the process, other crates printing packets see it too.
+    pub fn set_global(catalog: CommandCatalog) {
+        *GLOBAL.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(catalog));
+    }
+
+    /// Remove the installed catalog, packets are shown without names
+    pub fn clear_global() {
+        *GLOBAL.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Installed catalog
+    pub fn global() -> Option<Arc<CommandCatalog>> {
+        GLOBAL.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl fmt::Debug for CommandCatalog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(key, e)| (key, &e.name)))
+            .finish()
+    }
+}
+
+/// Write ` NAME` and ` decoded payload` after the command using the installed catalog
+pub(crate) fn write_global_name(
+    f: &mut fmt::Formatter,
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+) -> Result<bool, fmt::Error> {
+    // the lock is released before decoders run, they might print packets too
+    match CommandCatalog::global() {
+        Some(catalog) => catalog.write_name(f, address, command, data),
+        None => Ok(false),
+    }
+}
+
+#[test]
+fn command_catalog_test() {
+    let mut catalog = CommandCatalog::new();
+    catalog
+        .register(None, 0x02, "GET_INFO")
+        .register(Some(0x55), 0x02, "GET_VERSION")
+        .register_with_decoder(Some(0x55), 0x10, "SET_RELAY", |data| match data {
+            [relay, mode] => Some(format!("relay={} mode={}", relay, mode)),
+            _ => None,
+        });
+    assert_eq!(catalog.name(Some(0x55), 0x02), Some("GET_VERSION"));
+    assert_eq!(catalog.name(Some(0x12), 0x02), Some("GET_INFO"));
+    assert_eq!(catalog.name(None, 0x02), Some("GET_INFO"));
+    assert_eq!(catalog.name(None, 0x10), None);
+    assert_eq!(
+        catalog.decode(Some(0x55), 0x10, &[2, 1]),
+        Some("relay=2 mode=1".to_string())
+    );
+    assert_eq!(catalog.decode(Some(0x55), 0x10, &[2]), None);
+    assert_eq!(catalog.decode(None, 0x02, &[]), None);
+
+    // the installed catalog is checked by `tests/catalog.rs`, other tests print packets too
+    let p = crate::PacketRef {
+        address: Some(0x55),
+        command: 0x10,
+        data: &[2, 1],
+    };
+    let log = crate::PacketFormatter::new();
+    assert_eq!(
+        log.display_with_catalog(p, &catalog).to_string(),
+        "ADDR: 0x55\nCMD:  0x10 SET_RELAY relay=2 mode=1\n"
+    );
+    let p = crate::PacketRef { data: &[2], ..p };
+    assert_eq!(
+        log.display_with_catalog(p, &catalog).to_string(),
+        "ADDR: 0x55\nCMD:  0x10 SET_RELAY\nDATA: 1 bytes\n     \
+         0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f\n\
+         00: 02 \n"
+    );
+    let raw = log.with_compact(true).with_names(false);
+    assert_eq!(
+        raw.display_with_catalog(p, &catalog).to_string(),
+        "ADDR: 0x55 CMD: 0x10 DATA: 02"
+    );
+}
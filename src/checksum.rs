@@ -0,0 +1,122 @@
+//! Frame checksums: CRC-8 (default), CRC-16/CCITT and no checksum
+
+use crate::CRC_INIT;
+
+/// Checksum appended to every frame
+///
+/// The checksum is calculated over the destuffed frame starting from FEND. `LEN` low bytes
+/// of `value()` are sent MSB first right after the data (and stuffed as any other byte).
+pub trait Checksum: Default {
+    /// Trailer length in bytes: 0, 1 or 2
+    const LEN: usize;
+
+    /// Update checksum with one byte
+    fn update(&mut self, byte: u8);
+
+    /// Current checksum value
+    fn value(&self) -> u16;
+
+    /// Calculate checksum of data in a slice
+    fn calculate(data: &[u8]) -> u16 {
+        let mut c = Self::default();
+        for b in data {
+            c.update(*b);
+        }
+        c.value()
+    }
+}
+
+/// CRC-8 (Dallas/Maxim polynomial, reflected, init 0xDE), the Wake default
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Crc8(u8);
+
+impl Default for Crc8 {
+    fn default() -> Self {
+        Crc8(CRC_INIT)
+    }
+}
+
+impl Checksum for Crc8 {
+    const LEN: usize = 1;
+
+    fn update(&mut self, byte: u8) {
+        let mut b = byte;
+        for _ in 0..8 {
+            self.0 = if (b ^ self.0) & 1 == 1 {
+                ((self.0 ^ 0x18) >> 1) | 0x80
+            } else {
+                (self.0 >> 1) & !0x80
+            };
+            b >>= 1;
+        }
+    }
+
+    fn value(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+/// CRC-16/CCITT (polynomial 0x1021, init 0xFFFF), for long payloads
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Crc16(u16);
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Crc16(0xFFFF)
+    }
+}
+
+impl Checksum for Crc16 {
+    const LEN: usize = 2;
+
+    fn update(&mut self, byte: u8) {
+        self.0 ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            self.0 = if self.0 & 0x8000 != 0 {
+                (self.0 << 1) ^ 0x1021
+            } else {
+                self.0 << 1
+            };
+        }
+    }
+
+    fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// No checksum, frames end right after the data
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NoCrc;
+
+impl Checksum for NoCrc {
+    const LEN: usize = 0;
+
+    fn update(&mut self, _byte: u8) {}
+
+    fn value(&self) -> u16 {
+        0
+    }
+}
+
+#[test]
+fn crc8_test() {
+    assert_eq!(Crc8::calculate(&[1, 2, 3, 4, 5]), 0xd6);
+    assert_eq!(Crc8::calculate(&[0xc0, 0x03, 0x00]), 0xeb);
+    assert_eq!(
+        Crc8::calculate(&[0xc0, 0x89, 0x03, 0x05, 1, 2, 3, 4, 5]),
+        0x69
+    );
+}
+
+#[test]
+fn crc16_test() {
+    // CRC-16/CCITT-FALSE check value
+    assert_eq!(Crc16::calculate(b"123456789"), 0x29b1);
+    assert_eq!(Crc16::calculate(&[]), 0xffff);
+}
+
+#[test]
+fn no_crc_test() {
+    assert_eq!(NoCrc::calculate(&[1, 2, 3]), 0);
+}
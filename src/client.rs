@@ -0,0 +1,651 @@
+//! Blocking request/response client over a byte transport
+
+use core::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::frame::encode_to_vec_with;
+use crate::std_commands::{self, ErrReply, ECHO, ERR, INFO};
+use crate::{Checksum, Config, Crc8, ErrorCode, Packet, StreamDecoder, WakeError};
+
+/// Default reply timeout of [`WakeClient`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Receive buffer length of [`WakeClient`]
+const RECV_BUF_LEN: usize = 64;
+
+/// Byte link to a device: serial port, socket, etc.
+pub trait Transport {
+    /// Send all bytes
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    /// Receive some bytes, waiting until the deadline at most
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - receive buffer
+    /// * `deadline` - time to give up
+    ///
+    /// # Output
+    ///
+    /// * `io::Result<usize>` - number of received bytes, `TimedOut` or `WouldBlock` if nothing
+    ///   is received before the deadline, `Ok(0)` if the link is closed
+    ///
+    fn recv(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<usize>;
+}
+
+/// [`Transport`] over a `std::io` stream, e.g. a serial port
+///
+/// Reads are repeated after `TimedOut` and `WouldBlock` until the deadline, so the stream
+/// must have a read timeout shorter than the client one (serial ports usually do) or be
+/// non-blocking.
+#[derive(Debug)]
+pub struct IoTransport<T> {
+    stream: T,
+}
+
+impl<T: Read + Write> IoTransport<T> {
+    /// Wrap a stream
+    pub fn new(stream: T) -> Self {
+        IoTransport { stream }
+    }
+
+    /// Underlying stream
+    pub fn get_ref(&self) -> &T {
+        &self.stream
+    }
+
+    /// Underlying stream
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// Unwrap the stream
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
+
+impl<T: Read + Write> Transport for IoTransport<T> {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.write_all(bytes)?;
+        self.stream.flush()
+    }
+
+    fn recv(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(e) if Instant::now() >= deadline => return Err(e),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => (),
+                received => return received,
+            }
+        }
+    }
+}
+
+/// How many times a request is repeated and how long to wait before every retry
+///
+/// The delay is doubled after every retry. Requests are repeated after a timeout,
+/// a broken reply frame or a mismatched reply, never after other I/O errors.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Send every request once
+    pub const fn none() -> Self {
+        RetryPolicy {
+            retries: 0,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Repeat requests `retries` times, wait `backoff`, `2 * backoff`, `4 * backoff`, etc.
+    pub const fn new(retries: u32, backoff: Duration) -> Self {
+        RetryPolicy { retries, backoff }
+    }
+
+    /// Delay before the retry, starting from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1 << retry.min(31))
+    }
+}
+
+/// Request failure, see [`WakeClient::request`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ClientError {
+    /// No reply before the deadline
+    Timeout,
+    /// Request can't be encoded or the reply frame is broken
+    Protocol(WakeError),
+    /// Only replies of other devices or to other commands are received
+    Mismatch {
+        /// Address of the last received reply
+        address: Option<u8>,
+        /// Command of the last received reply
+        command: u8,
+    },
+    /// I/O error of the transport, the link is closed for `UnexpectedEof`
+    Io(ErrorKind),
+    /// The device replied with `ERR` and the error code
+    Device(ErrorCode),
+    /// The reply data is not what the command returns, e.g. a wrong `ECHO` reply
+    InvalidReply,
+}
+
+impl ClientError {
+    /// The request might succeed if it is sent again
+    ///
+    /// Device errors are retryable if the device is busy, not ready or received a broken request.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Timeout | ClientError::Mismatch { .. } => true,
+            ClientError::Device(code) => matches!(
+                *code,
+                ErrorCode::TX | ErrorCode::BUSY | ErrorCode::NOT_READY
+            ),
+            ClientError::Protocol(e) => !matches!(
+                e,
+                WakeError::WrongAddrRange { .. }
+                    | WakeError::WrongCmdRange { .. }
+                    | WakeError::TooLongData { .. }
+            ),
+            ClientError::Io(_) | ClientError::InvalidReply => false,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Timeout => f.write_str("no reply before the deadline"),
+            ClientError::Protocol(e) => write!(f, "protocol error: {}", e),
+            ClientError::Mismatch {
+                address: Some(address),
+                command,
+            } => write!(
+                f,
+                "mismatched reply: address 0x{:02X}, command 0x{:02X}",
+                address, command
+            ),
+            ClientError::Mismatch {
+                address: None,
+                command,
+            } => write!(f, "mismatched reply: command 0x{:02X}", command),
+            ClientError::Io(kind) => write!(f, "I/O error: {}", io::Error::from(*kind)),
+            ClientError::Device(code) => write!(f, "device error: {}", code),
+            ClientError::InvalidReply => f.write_str("invalid reply data"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<WakeError> for ClientError {
+    fn from(e: WakeError) -> Self {
+        ClientError::Protocol(e)
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e.kind())
+    }
+}
+
+/// Match the packet to the request: same command or an `ERR` reply with the error code and,
+/// if the request has one, same address
+///
+/// # Output
+///
+/// * `Option<Result<Packet, ClientError>>` - reply, `Device` for an `ERR` reply, `None` if the
+///   packet is not a reply to the request
+///
+pub(crate) fn match_reply(
+    request_address: Option<u8>,
+    request_command: u8,
+    reply: Packet,
+) -> Option<Result<Packet, ClientError>> {
+    if request_address.is_some() && reply.address.map(u8::from) != request_address {
+        return None;
+    }
+    if reply.command == request_command {
+        return Some(Ok(reply));
+    }
+    match (reply.command.get(), reply.data()) {
+        (ERR, Some(data)) => ErrReply::parse(data).map(|e| Err(ClientError::Device(e.code))),
+        _ => None,
+    }
+}
+
+/// Blocking client: sends a request and waits for the matching reply
+///
+/// Broken frames and replies of other devices or to other commands (e.g. late replies to a
+/// timed out request) are skipped until the deadline, an `ERR` reply ends the request with
+/// [`ClientError::Device`]. One request is sent at a time.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate wake_rs;
+/// use std::net::TcpStream;
+/// use std::time::Duration;
+/// use wake_rs::{IoTransport, RetryPolicy, WakeClient};
+///
+/// let stream = TcpStream::connect("192.168.0.10:5000").unwrap();
+/// stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+/// let mut client = WakeClient::new(IoTransport::new(stream))
+///     .with_timeout(Duration::from_millis(200))
+///     .with_retry(RetryPolicy::new(3, Duration::from_millis(20)));
+/// let reply = client.request(Some(0x12), 0x10, &[2, 1]).unwrap();
+/// println!("{}", reply);
+/// ```
+#[derive(Debug)]
+pub struct WakeClient<T, C = Crc8> {
+    transport: T,
+    config: Config<C>,
+    decoder: StreamDecoder<C>,
+    timeout: Duration,
+    retry: RetryPolicy,
+    tx: Vec<u8>,
+}
+
+impl<T: Transport> WakeClient<T> {
+    /// Create a CRC-8 client, 100 ms reply timeout, no retries
+    pub fn new(transport: T) -> Self {
+        Self::with_config(transport, Config::new())
+    }
+}
+
+impl<T: Transport, C: Checksum> WakeClient<T, C> {
+    /// Create a client with the given link configuration
+    pub fn with_config(transport: T, config: Config<C>) -> Self {
+        WakeClient {
+            transport,
+            config,
+            decoder: StreamDecoder::with_config(config),
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::none(),
+            tx: Vec::new(),
+        }
+    }
+
+    /// Set the default reply timeout, per attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the retry policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send a request and wait for the reply with the default timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address (optional) [0 - 127]
+    /// * `command` - command [0 - 127]
+    /// * `payload` - request data, might be empty
+    ///
+    /// # Output
+    ///
+    /// * `Result<Packet, ClientError>` - reply with the same command (and address) or the
+    ///   error of the last attempt: `Timeout`, or the last broken frame (`Protocol`) or
+    ///   mismatched reply received before the deadline
+    ///
+    pub fn request(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+    ) -> Result<Packet, ClientError> {
+        self.request_with_timeout(address, command, payload, self.timeout)
+    }
+
+    /// Send a request and wait for the reply, `timeout` is applied to every attempt
+    pub fn request_with_timeout(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Result<Packet, ClientError> {
+        self.tx.clear();
+        encode_to_vec_with(address, command, payload, &mut self.tx, &self.config)?;
+        let mut retry = 0;
+        loop {
+            match self.attempt(address, command, timeout) {
+                Err(e) if e.is_retryable() && retry < self.retry.retries => {
+                    thread::sleep(self.retry.delay(retry));
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the encoded request once and wait for the reply
+    ///
+    /// Broken frames and mismatched replies are skipped, the last one is returned at the deadline.
+    fn attempt(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        timeout: Duration,
+    ) -> Result<Packet, ClientError> {
+        // 1: Send the request, a partial frame of the previous one is dropped
+        self.decoder.reset();
+        self.transport.send(&self.tx)?;
+        // 2: Wait for the reply, skip broken frames and other packets
+        let deadline = Instant::now() + timeout;
+        let mut skipped = None;
+        let mut buf = [0u8; RECV_BUF_LEN];
+        loop {
+            if Instant::now() >= deadline {
+                return Err(skipped.unwrap_or(ClientError::Timeout));
+            }
+            let n = match self.transport.recv(&mut buf, deadline) {
+                Ok(0) => return Err(ClientError::Io(ErrorKind::UnexpectedEof)),
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    return Err(skipped.unwrap_or(ClientError::Timeout))
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ClientError::Io(e.kind())),
+            };
+            for decoded in self.decoder.feed(&buf[..n]) {
+                let p = match decoded {
+                    Ok(p) => p,
+                    Err(e) => {
+                        skipped = Some(e.into());
+                        continue;
+                    }
+                };
+                let other = ClientError::Mismatch {
+                    address: p.address.map(u8::from),
+                    command: p.command.get(),
+                };
+                if let Some(result) = match_reply(address, command, p) {
+                    return result;
+                }
+                skipped = Some(other);
+            }
+        }
+    }
+
+    /// Check the link: send `ECHO` and compare the reply data
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address (optional) [0 - 127]
+    /// * `payload` - data to send back, might be empty
+    ///
+    /// # Output
+    ///
+    /// * `Result<(), ClientError>` - `InvalidReply` if the device sent other data back
+    ///
+    pub fn ping(&mut self, address: Option<u8>, payload: &[u8]) -> Result<(), ClientError> {
+        let reply = self.request(address, ECHO, payload)?;
+        std_commands::check_echo(payload, &reply)
+    }
+
+    /// Device description replied to `INFO`, e.g. `Relay shield v1.0`
+    ///
+    /// # Output
+    ///
+    /// * `Result<String, ClientError>` - description, `InvalidReply` if it is not UTF-8
+    ///
+    pub fn info(&mut self, address: Option<u8>) -> Result<String, ClientError> {
+        let reply = self.request(address, INFO, &[])?;
+        std_commands::info_text(&reply)
+    }
+
+    /// Underlying transport
+    pub fn get_ref(&self) -> &T {
+        &self.transport
+    }
+
+    /// Underlying transport
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+/// Device simulator: replies to every request with the frames `reply` returns
+#[cfg(test)]
+struct TestDevice<F> {
+    reply: F,
+    requests: Vec<Vec<u8>>,
+    rx: Vec<u8>,
+}
+
+#[cfg(test)]
+impl<F: FnMut(usize, Packet) -> Vec<u8>> Transport for TestDevice<F> {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        use crate::Decode;
+        let request = bytes.to_vec().decode().unwrap();
+        self.rx = (self.reply)(self.requests.len(), request);
+        self.requests.push(bytes.to_vec());
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8], _deadline: Instant) -> io::Result<usize> {
+        if self.rx.is_empty() {
+            return Err(ErrorKind::TimedOut.into());
+        }
+        // a few bytes at a time
+        let n = self.rx.len().min(buf.len()).min(3);
+        buf[..n].copy_from_slice(&self.rx[..n]);
+        self.rx.drain(..n);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+use crate::{Encode, FEND};
+
+#[test]
+fn wake_client_test() {
+    let echo = |_, p: Packet| p.encode().unwrap();
+    let device = TestDevice {
+        reply: echo,
+        requests: vec![],
+        rx: vec![],
+    };
+    let mut client = WakeClient::new(device);
+    let reply = client.request(Some(0x12), 0x10, &[2, 1]).unwrap();
+    assert_eq!(reply.address.unwrap(), 0x12);
+    assert_eq!(reply.data(), Some(&[2, 1][..]));
+    assert_eq!(
+        client.request(Some(0x12), 0x80, &[]),
+        Err(ClientError::Protocol(WakeError::WrongCmdRange {
+            command: 0x80
+        }))
+    );
+    assert_eq!(client.get_ref().requests.len(), 1);
+
+    // a late reply of another command and a reply of another device are skipped
+    let other = |_, p: Packet| {
+        let mut rx = Packet::builder()
+            .command(1)
+            .build()
+            .unwrap()
+            .encode()
+            .unwrap();
+        let mut other_device = p.clone();
+        other_device.address = Some(crate::Address::new(0x13).unwrap());
+        rx.extend(other_device.encode().unwrap());
+        rx.extend(p.encode().unwrap());
+        rx
+    };
+    let mut client = WakeClient::new(TestDevice {
+        reply: other,
+        requests: vec![],
+        rx: vec![],
+    });
+    assert_eq!(client.request(Some(0x12), 2, &[]).unwrap().command, 2);
+    // without the address the first reply of any device is taken
+    assert_eq!(client.request(None, 2, &[]).unwrap().address.unwrap(), 0x13);
+
+    // only mismatched replies
+    let mismatch = |_, _| {
+        Packet::builder()
+            .command(1)
+            .build()
+            .unwrap()
+            .encode()
+            .unwrap()
+    };
+    let mut client = WakeClient::new(TestDevice {
+        reply: mismatch,
+        requests: vec![],
+        rx: vec![],
+    });
+    assert_eq!(
+        client.request(None, 2, &[]),
+        Err(ClientError::Mismatch {
+            address: None,
+            command: 1
+        })
+    );
+}
+
+#[test]
+fn wake_client_retry_test() {
+    // no reply to the first two requests, a broken reply to the third one
+    let flaky = |i, p: Packet| match i {
+        0 | 1 => vec![],
+        2 => {
+            let mut rx = p.encode().unwrap();
+            *rx.last_mut().unwrap() ^= 0xff;
+            rx
+        }
+        _ => p.encode().unwrap(),
+    };
+    let retry = RetryPolicy::new(3, Duration::from_millis(1));
+    let mut client = WakeClient::new(TestDevice {
+        reply: flaky,
+        requests: vec![],
+        rx: vec![],
+    })
+    .with_retry(retry);
+    assert_eq!(client.request(None, 5, &[1]).unwrap().command, 5);
+    assert_eq!(client.get_ref().requests.len(), 4);
+
+    let mut client = WakeClient::new(TestDevice {
+        reply: flaky,
+        requests: vec![],
+        rx: vec![],
+    })
+    .with_retry(RetryPolicy::new(2, Duration::ZERO));
+    let e = client.request(None, 5, &[1]).unwrap_err();
+    assert!(matches!(
+        e,
+        ClientError::Protocol(WakeError::WrongPacketCrc { .. })
+    ));
+    assert_eq!(client.get_ref().requests.len(), 3);
+
+    let mut client = WakeClient::new(TestDevice {
+        reply: flaky,
+        requests: vec![],
+        rx: vec![],
+    });
+    assert_eq!(client.request(None, 5, &[1]), Err(ClientError::Timeout));
+
+    // line noise breaks a frame before the reply, the request is not repeated
+    let noisy = |_, p: Packet| {
+        let mut rx = vec![FEND, 0x05, 0x01, 0x00];
+        rx.extend(p.encode().unwrap());
+        rx
+    };
+    let mut client = WakeClient::new(TestDevice {
+        reply: noisy,
+        requests: vec![],
+        rx: vec![],
+    })
+    .with_retry(retry);
+    assert_eq!(
+        client.request(None, 5, &[1]).unwrap().into_data(),
+        Some(vec![1])
+    );
+    assert_eq!(client.get_ref().requests.len(), 1);
+
+    assert_eq!(retry.delay(0), Duration::from_millis(1));
+    assert_eq!(retry.delay(3), Duration::from_millis(8));
+    assert_eq!(retry.delay(40), retry.delay(31));
+}
+
+#[test]
+fn io_transport_test() {
+    // the other end is closed
+    let mut client = WakeClient::new(IoTransport::new(io::Cursor::new(vec![])))
+        .with_retry(RetryPolicy::new(3, Duration::ZERO));
+    assert_eq!(
+        client.request(None, 1, &[]),
+        Err(ClientError::Io(ErrorKind::UnexpectedEof))
+    );
+}
+
+#[test]
+fn wake_client_std_commands_test() {
+    let mut server = crate::WakeServer::new().with_address(0x12);
+    server
+        .register_std_commands("Relay shield v1.0")
+        .register(None, 0x10, |_: &Packet| Err(ErrorCode::BUSY));
+    let device = move |_, p: Packet| match server.handle(&p) {
+        Some(reply) => reply.encode().unwrap(),
+        None => vec![],
+    };
+    let mut client = WakeClient::new(TestDevice {
+        reply: device,
+        requests: vec![],
+        rx: vec![],
+    })
+    .with_retry(RetryPolicy::new(2, Duration::ZERO));
+    assert_eq!(client.ping(Some(0x12), &[0xC0, 1, 2]), Ok(()));
+    assert_eq!(client.ping(None, &[]), Ok(()));
+    assert_eq!(client.info(Some(0x12)), Ok("Relay shield v1.0".to_string()));
+    assert_eq!(client.get_ref().requests.len(), 3);
+    // ERR replies, a busy device is asked again
+    assert_eq!(
+        client.request(Some(0x12), 0x20, &[]),
+        Err(ClientError::Device(ErrorCode::PARAMETER))
+    );
+    assert_eq!(client.get_ref().requests.len(), 4);
+    let e = client.request(Some(0x12), 0x10, &[]).unwrap_err();
+    assert_eq!(e, ClientError::Device(ErrorCode::BUSY));
+    assert!(e.is_retryable());
+    assert_eq!(e.to_string(), "device error: device is busy");
+    assert_eq!(client.get_ref().requests.len(), 7);
+
+    // a device which doesn't know the service commands
+    let wrong = |_, p: Packet| {
+        let mut reply = p.clone();
+        reply.set_data([0xff]).unwrap();
+        reply.encode().unwrap()
+    };
+    let mut client = WakeClient::new(TestDevice {
+        reply: wrong,
+        requests: vec![],
+        rx: vec![],
+    });
+    assert_eq!(client.ping(None, &[1]), Err(ClientError::InvalidReply));
+    assert_eq!(client.info(None), Err(ClientError::InvalidReply));
+}
@@ -0,0 +1,194 @@
+//! tokio-util codec, turns any `AsyncRead + AsyncWrite` into a stream and a sink of packets
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{Checksum, Config, Crc8, Packet, PacketRef, StreamDecoder, StreamError, WakeError};
+
+/// Wake codec for `tokio_util::codec::Framed`, `FramedRead` and `FramedWrite`
+///
+/// Broken frames never end the stream: they are dropped, counted in [`WakeCodec::dropped`],
+/// and the decoder resynchronizes on the next FEND. The error of the last one is kept in
+/// [`WakeCodec::last_error`]. Decoding fails on I/O errors only, as `FramedRead` ends the
+/// stream after any decoder error.
+/// The maximum frame length is set by the link configuration, see [`WakeCodec::with_config`].
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use futures::{SinkExt, StreamExt};
+/// use tokio_util::codec::Framed;
+/// use wake_rs::{Packet, WakeCodec};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (host, device) = tokio::io::duplex(64);
+/// let mut host = Framed::new(host, WakeCodec::default());
+/// let mut device = Framed::new(device, WakeCodec::default());
+///
+/// host.send(Packet::builder().command(3).build().unwrap()).await.unwrap();
+/// let p = device.next().await.unwrap().unwrap();
+/// assert_eq!(p.command, 3);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WakeCodec<C = Crc8> {
+    decoder: StreamDecoder<C>,
+    config: Config<C>,
+    dropped: usize,
+    last_error: Option<WakeError>,
+}
+
+/// CRC-8 codec, so that `WakeCodec::default()` needs no type annotations
+impl Default for WakeCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WakeCodec {
+    /// Create a new CRC-8 codec
+    pub fn new() -> Self {
+        Self::with_config(Config::new())
+    }
+}
+
+impl<C: Checksum> WakeCodec<C> {
+    /// Create a new codec with the given link configuration
+    ///
+    /// Frames with more than `config.max_data_len()` bytes of data are dropped as soon as
+    /// their header arrives and such packets are never sent.
+    pub fn with_config(config: Config<C>) -> Self {
+        WakeCodec {
+            decoder: StreamDecoder::with_config(config),
+            config,
+            dropped: 0,
+            last_error: None,
+        }
+    }
+
+    /// Number of broken frames dropped so far
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Error of the last dropped frame, `None` if no frame is dropped yet
+    pub fn last_error(&self) -> Option<WakeError> {
+        self.last_error
+    }
+}
+
+impl<C: Checksum> Decoder for WakeCodec<C> {
+    type Item = Packet;
+    type Error = StreamError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>, StreamError> {
+        // bytes are moved into the stream decoder, so a partial frame is never parsed twice
+        let found = src
+            .iter()
+            .enumerate()
+            .find_map(|(i, byte)| match self.decoder.push(*byte) {
+                Some(Ok(p)) => Some((i + 1, p)),
+                Some(Err(e)) => {
+                    self.dropped += 1;
+                    self.last_error = Some(e);
+                    None
+                }
+                None => None,
+            });
+        match found {
+            Some((n, p)) => {
+                src.advance(n);
+                Ok(Some(p))
+            }
+            None => {
+                src.clear();
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'a, C: Checksum> Encoder<PacketRef<'a>> for WakeCodec<C> {
+    type Error = StreamError;
+
+    fn encode(&mut self, item: PacketRef<'a>, dst: &mut BytesMut) -> Result<(), StreamError> {
+        dst.extend(item.encode_iter_with(&self.config)?);
+        Ok(())
+    }
+}
+
+impl<C: Checksum> Encoder<Packet> for WakeCodec<C> {
+    type Error = StreamError;
+
+    fn encode(&mut self, item: Packet, dst: &mut BytesMut) -> Result<(), StreamError> {
+        self.encode(PacketRef::from(&item), dst)
+    }
+}
+
+#[cfg(test)]
+use crate::{Encode, FEND};
+
+#[test]
+fn codec_decode_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, FEND, 0xeb])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut codec = WakeCodec::new();
+    let mut src = BytesMut::new();
+    assert_eq!(codec.last_error(), None);
+    // noise, broken frame, frame split in two reads
+    src.extend_from_slice(&[0x00, FEND, 0x03, 0x01, 0xdb, 0x00]);
+    src.extend_from_slice(&encoded[..4]);
+    assert!(codec.decode(&mut src).unwrap().is_none());
+    assert!(src.is_empty());
+    src.extend_from_slice(&encoded[4..]);
+    src.extend_from_slice(&encoded);
+    let p = codec.decode(&mut src).unwrap().unwrap();
+    assert_eq!(p.data, wp.data);
+    assert_eq!(src[..], encoded[..]);
+    assert_eq!(codec.decode(&mut src).unwrap(), Some(wp));
+    assert_eq!(codec.decode(&mut src).unwrap(), None);
+    assert_eq!(codec.dropped(), 1);
+    assert_eq!(
+        codec.last_error(),
+        Some(WakeError::DestuffingFailed {
+            offset: 4,
+            byte: 0x00
+        })
+    );
+}
+
+#[test]
+fn codec_encode_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![1, 2, 3])
+        .build()
+        .unwrap();
+    let mut codec = WakeCodec::with_config(Config::new().with_max_data_len(2));
+    let mut dst = BytesMut::new();
+    assert!(matches!(
+        codec.encode(PacketRef::from(&wp), &mut dst),
+        Err(StreamError::Wake(WakeError::TooLongData { len: 3, max: 2 }))
+    ));
+    assert!(dst.is_empty());
+    let mut codec = WakeCodec::new();
+    codec.encode(PacketRef::from(&wp), &mut dst).unwrap();
+    codec.encode(wp, &mut dst).unwrap();
+    let encoded = PacketRef {
+        address: Some(0x40),
+        command: 0x40,
+        data: &[1, 2, 3],
+    }
+    .encode()
+    .unwrap();
+    assert_eq!(dst[..encoded.len()], encoded[..]);
+    assert_eq!(dst[encoded.len()..], encoded[..]);
+}
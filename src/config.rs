@@ -0,0 +1,94 @@
+//! Per-link configuration: checksum and maximum data length
+
+use core::{any, fmt, marker::PhantomData};
+
+use crate::{Checksum, Crc8, DATA_MAX_LEN};
+
+/// Link configuration shared by the encoder and the decoder
+///
+/// The checksum is selected by the type parameter (CRC-8 by default). Both the encoder
+/// and the decoder reject packets with more than `max_data_len()` bytes of data
+/// with `WakeError::TooLongData`.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{Config, Crc16};
+///
+/// // small device: CRC-16, 32 bytes of data at most
+/// let config = Config::new().with_checksum::<Crc16>().with_max_data_len(32);
+/// assert_eq!(config.max_data_len(), 32);
+/// ```
+pub struct Config<C = Crc8> {
+    max_data_len: usize,
+    checksum: PhantomData<C>,
+}
+
+impl Config {
+    /// Default configuration: CRC-8, `DATA_MAX_LEN` bytes of data
+    pub const fn new() -> Self {
+        Config {
+            max_data_len: DATA_MAX_LEN,
+            checksum: PhantomData,
+        }
+    }
+}
+
+impl<C: Checksum> Config<C> {
+    /// Set maximum data length, values above `DATA_MAX_LEN` are reduced to `DATA_MAX_LEN`
+    pub fn with_max_data_len(mut self, len: usize) -> Self {
+        self.max_data_len = len.min(DATA_MAX_LEN);
+        self
+    }
+
+    /// Use another checksum, the other settings are kept
+    pub fn with_checksum<D: Checksum>(self) -> Config<D> {
+        Config {
+            max_data_len: self.max_data_len,
+            checksum: PhantomData,
+        }
+    }
+
+    /// Maximum data length
+    pub fn max_data_len(&self) -> usize {
+        self.max_data_len
+    }
+}
+
+impl<C: Checksum> Default for Config<C> {
+    fn default() -> Self {
+        Config::new().with_checksum()
+    }
+}
+
+impl<C> Clone for Config<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Config<C> {}
+
+impl<C> fmt::Debug for Config<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("checksum", &any::type_name::<C>())
+            .field("max_data_len", &self.max_data_len)
+            .finish()
+    }
+}
+
+#[test]
+fn config_test() {
+    let config = Config::new();
+    assert_eq!(config.max_data_len(), DATA_MAX_LEN);
+    let config = config.with_max_data_len(1000);
+    assert_eq!(config.max_data_len(), DATA_MAX_LEN);
+    let config = config.with_max_data_len(32).with_checksum::<crate::NoCrc>();
+    assert_eq!(config.max_data_len(), 32);
+    assert_eq!(
+        Config::<crate::Crc16>::default().max_data_len(),
+        DATA_MAX_LEN
+    );
+}
@@ -0,0 +1,246 @@
+//! Device-side command dispatcher without heap: a static command table and fixed buffers
+
+use crate::frame::encode_into_with;
+use crate::std_commands::ERR;
+use crate::{
+    Address, Checksum, Config, Crc8, ErrorCode, StreamDecoder, DATA_MAX_LEN, ENCODED_MAX_LEN,
+};
+
+/// Command handler of [`Dispatcher`]
+///
+/// # Arguments
+///
+/// * `data` - request data, might be empty
+/// * `reply` - reply data buffer, the link maximum data length
+///
+/// # Output
+///
+/// * `Result<usize, ErrorCode>` - reply data length or the code of the `ERR` reply
+///
+pub type CommandFn = fn(data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode>;
+
+/// Device-side dispatcher for microcontrollers: nothing is allocated
+///
+/// Received bytes are fed to [`Dispatcher::push`], which decodes requests, calls the handler
+/// of the command from the table and returns the encoded reply frame. The reply has the
+/// command and the address of the request. Requests to other addresses are ignored,
+/// broadcast requests (address 0) are handled without replying, requests without address
+/// (point-to-point link) are answered. An unknown command or a handler error is answered with
+/// the `ERR` reply: command 0x01 and the error code ([`ErrorCode::PARAMETER`] for unknown
+/// commands). Broken frames are skipped.
+///
+/// Handlers of the service commands are in [`std_commands`](crate::std_commands).
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{CommandFn, Dispatcher, ErrorCode};
+///
+/// fn set_relay(data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+///     match data {
+///         [0..=3, 0..=4] => {
+///             reply[0] = 0; // done
+///             Ok(1)
+///         }
+///         _ => Err(ErrorCode::PARAMETER),
+///     }
+/// }
+///
+/// static COMMANDS: [(u8, CommandFn); 1] = [(0x10, set_relay)];
+///
+/// let mut dispatcher = Dispatcher::new(0x12, &COMMANDS);
+/// let mut request = [0u8; wake_rs::ENCODED_MAX_LEN];
+/// let n = wake_rs::encode_into(Some(0x12), 0x10, &[2, 1], &mut request).unwrap();
+/// let mut reply = None;
+/// for &byte in &request[..n] {
+///     // e.g. in the UART interrupt, the reply is sent as soon as the request is received
+///     if let Some(frame) = dispatcher.push(byte) {
+///         reply = Some(frame.to_vec());
+///     }
+/// }
+/// let mut reply = reply.unwrap();
+/// let p = wake_rs::PacketRef::decode(&mut reply).unwrap();
+/// assert_eq!((p.address, p.command, p.data), (Some(0x12), 0x10, &[0][..]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Dispatcher<'a, C = Crc8> {
+    address: u8,
+    commands: &'a [(u8, CommandFn)],
+    config: Config<C>,
+    decoder: StreamDecoder<C>,
+    reply: [u8; DATA_MAX_LEN],
+    tx: [u8; ENCODED_MAX_LEN],
+}
+
+impl<'a> Dispatcher<'a> {
+    /// Create a CRC-8 dispatcher
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address [1 - 127], requests to other addresses are ignored
+    /// * `commands` - command handlers, the first one of the command is called
+    ///
+    pub fn new(address: u8, commands: &'a [(u8, CommandFn)]) -> Self {
+        Self::with_config(address, commands, Config::new())
+    }
+}
+
+impl<'a, C: Checksum> Dispatcher<'a, C> {
+    /// Create a dispatcher with the given link configuration, see [`Dispatcher::new`]
+    pub fn with_config(address: u8, commands: &'a [(u8, CommandFn)], config: Config<C>) -> Self {
+        Dispatcher {
+            address,
+            commands,
+            config,
+            decoder: StreamDecoder::with_config(config),
+            reply: [0; DATA_MAX_LEN],
+            tx: [0; ENCODED_MAX_LEN],
+        }
+    }
+
+    /// Device address
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Change the device address, e.g. after it is set by the host
+    pub fn set_address(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    /// Drop the current frame and wait for the next start symbol
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+    }
+
+    /// Process one received byte
+    ///
+    /// # Arguments
+    ///
+    /// * `byte` - received byte
+    ///
+    /// # Output
+    ///
+    /// * `Option<&[u8]>` - encoded reply frame to send, `None` if the request is not complete
+    ///   yet or needs no reply
+    ///
+    pub fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        // 1: Decode and filter the request
+        let request = self.decoder.push_ref(byte)?.ok()?;
+        let (address, command) = (request.address, request.command);
+        let broadcast = address == Some(Address::BROADCAST.get());
+        if !broadcast && address.is_some_and(|a| a != self.address) {
+            return None;
+        }
+        // 2: Call the handler
+        let max_len = self.config.max_data_len().min(DATA_MAX_LEN);
+        let result = match self.commands.iter().find(|(c, _)| *c == command) {
+            Some((_, handler)) => handler(request.data, &mut self.reply[..max_len]),
+            None => Err(ErrorCode::PARAMETER),
+        };
+        if broadcast {
+            return None;
+        }
+        // 3: Encode the reply
+        let (command, len) = match result {
+            Ok(len) if len <= max_len => (command, len),
+            Ok(_) => (ERR, self.error(ErrorCode::TX)),
+            Err(code) => (ERR, self.error(code)),
+        };
+        let n = encode_into_with(
+            address,
+            command,
+            &self.reply[..len],
+            &mut self.tx,
+            &self.config,
+        )
+        .ok()?;
+        Some(&self.tx[..n])
+    }
+
+    /// Put the error code into the reply data
+    fn error(&mut self, code: ErrorCode) -> usize {
+        self.reply[0] = code.0;
+        1
+    }
+}
+
+#[cfg(test)]
+use crate::{encode_into, HeaplessPacket};
+
+#[cfg(test)]
+fn test_echo(data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    reply[..data.len()].copy_from_slice(data);
+    Ok(data.len())
+}
+
+#[cfg(test)]
+fn test_busy(_: &[u8], _: &mut [u8]) -> Result<usize, ErrorCode> {
+    Err(ErrorCode::BUSY)
+}
+
+#[cfg(test)]
+fn test_overflow(_: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    Ok(reply.len() + 1)
+}
+
+#[cfg(test)]
+type Reply = Option<HeaplessPacket<4>>;
+
+#[cfg(test)]
+fn reply(address: Option<u8>, command: u8, data: &[u8]) -> Reply {
+    Some(HeaplessPacket::new(address, command, data).unwrap())
+}
+
+#[test]
+fn dispatcher_test() {
+    static COMMANDS: [(u8, CommandFn); 3] =
+        [(0x02, test_echo), (0x04, test_busy), (0x05, test_overflow)];
+    let mut dispatcher = Dispatcher::new(0x12, &COMMANDS);
+    let mut transact = |address: Option<u8>, command: u8, data: &[u8]| -> Reply {
+        let mut frame = [0u8; ENCODED_MAX_LEN];
+        let n = encode_into(address, command, data, &mut frame).unwrap();
+        let mut reply = None;
+        for (i, &byte) in frame[..n].iter().enumerate() {
+            if let Some(tx) = dispatcher.push(byte) {
+                // the reply is ready after the last byte
+                assert_eq!(i, n - 1);
+                reply = Some(HeaplessPacket::decode(tx).unwrap());
+            }
+        }
+        reply
+    };
+    assert_eq!(
+        transact(Some(0x12), 0x02, &[0xC0, 0xDB]),
+        reply(Some(0x12), 0x02, &[0xC0, 0xDB])
+    );
+    assert_eq!(transact(None, 0x02, &[]), reply(None, 0x02, &[]));
+    assert_eq!(
+        transact(Some(0x12), 0x04, &[]),
+        reply(Some(0x12), ERR, &[0x02])
+    );
+    assert_eq!(
+        transact(Some(0x12), 0x05, &[]),
+        reply(Some(0x12), ERR, &[0x01])
+    );
+    assert_eq!(transact(None, 0x7f, &[]), reply(None, ERR, &[0x04]));
+    // other address and broadcast
+    assert_eq!(transact(Some(0x13), 0x02, &[1]), None);
+    assert_eq!(transact(Some(0), 0x02, &[1]), None);
+
+    // a broken frame is skipped, the smaller link maximum data length is passed to handlers
+    let config = Config::new().with_max_data_len(4);
+    let mut dispatcher = Dispatcher::with_config(0x12, &COMMANDS, config);
+    let noise = [0xC0, 0x92, 0x02, 0x01, 0x00, 0x00];
+    assert!(noise.iter().all(|&byte| dispatcher.push(byte).is_none()));
+    let mut frame = [0u8; ENCODED_MAX_LEN];
+    let n = encode_into(Some(0x12), 0x05, &[], &mut frame).unwrap();
+    let mut last = None;
+    for &byte in &frame[..n] {
+        if let Some(tx) = dispatcher.push(byte) {
+            last = Some(HeaplessPacket::decode(tx).unwrap());
+        }
+    }
+    assert_eq!(last, reply(Some(0x12), ERR, &[0x01]));
+}
@@ -0,0 +1,241 @@
+//! Byte-by-byte encoder, e.g. for a UART TX interrupt
+
+use core::iter::FusedIterator;
+
+use crate::{Checksum, Config, Crc8, PacketRef, WakeError, ADDR_MASK, FEND, FESC, TFEND, TFESC};
+
+/// Next field to be sent
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Step {
+    Start,
+    Address,
+    Command,
+    Len,
+    Data(usize),
+    Trailer(usize),
+    Done,
+}
+
+/// Iterator over the stuffed wire bytes of a packet, no buffer is needed
+///
+/// The CRC is calculated while the bytes are sent. The output is exactly the same as
+/// [`encode_into`](crate::encode_into) produces.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::PacketRef;
+///
+/// let p = PacketRef { address: Some(0x12), command: 3, data: &[0x00, 0xeb] };
+/// let mut tx = p.encode_iter().unwrap();
+/// // TX register is empty
+/// while let Some(byte) = tx.next() {
+///     // write byte to the TX register
+///     # let _ = byte;
+/// }
+/// let encoded: Vec<u8> = p.encode_iter().unwrap().collect();
+/// assert_eq!(encoded, vec![0xC0, 0x92, 0x03, 0x02, 0x00, 0xeb, 114]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EncodeIter<'a, C = Crc8> {
+    packet: PacketRef<'a>,
+    step: Step,
+    crc: C,
+    escaped: Option<u8>,
+}
+
+impl<'a, C: Checksum> EncodeIter<'a, C> {
+    /// Create an iterator over the encoded packet
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - packet to be sent
+    /// * `config` - link configuration
+    ///
+    /// # Output
+    ///
+    /// * `Result<EncodeIter, WakeError>` - iterator or the error `encode_into` would return
+    ///   for this packet, except `BufferTooSmall`
+    ///
+    pub fn new(packet: PacketRef<'a>, config: &Config<C>) -> Result<Self, WakeError> {
+        if let Some(address) = packet.address.filter(|a| *a > 0x7f) {
+            return Err(WakeError::WrongAddrRange { address });
+        }
+        if packet.command > 0x7f {
+            return Err(WakeError::WrongCmdRange {
+                command: packet.command,
+            });
+        }
+        if packet.data.len() > config.max_data_len() {
+            return Err(WakeError::TooLongData {
+                len: packet.data.len(),
+                max: config.max_data_len(),
+            });
+        }
+        Ok(EncodeIter {
+            packet,
+            step: Step::Start,
+            crc: C::default(),
+            escaped: None,
+        })
+    }
+
+    /// Get the next destuffed byte of the frame and update the CRC
+    fn next_raw(&mut self) -> Option<u8> {
+        loop {
+            let (byte, next) = match self.step {
+                Step::Start => (Some(FEND), Step::Address),
+                Step::Address => (self.packet.address.map(|a| a | ADDR_MASK), Step::Command),
+                Step::Command => (Some(self.packet.command), Step::Len),
+                Step::Len => (Some(self.packet.data.len() as u8), Step::Data(0)),
+                Step::Data(i) => match self.packet.data.get(i) {
+                    Some(b) => (Some(*b), Step::Data(i + 1)),
+                    None => (None, Step::Trailer(0)),
+                },
+                Step::Trailer(i) if i < C::LEN => {
+                    // the trailer is not covered by the CRC
+                    let value = self.crc.value().to_be_bytes();
+                    self.step = Step::Trailer(i + 1);
+                    return Some(value[value.len() - C::LEN + i]);
+                }
+                Step::Trailer(_) | Step::Done => {
+                    self.step = Step::Done;
+                    return None;
+                }
+            };
+            self.step = next;
+            if let Some(b) = byte {
+                self.crc.update(b);
+                return Some(b);
+            }
+        }
+    }
+}
+
+impl<'a, C: Checksum> Iterator for EncodeIter<'a, C> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if let Some(b) = self.escaped.take() {
+            return Some(b);
+        }
+        let start = self.step == Step::Start;
+        match self.next_raw()? {
+            FEND if !start => {
+                self.escaped = Some(TFEND);
+                Some(FESC)
+            }
+            FESC => {
+                self.escaped = Some(TFESC);
+                Some(FESC)
+            }
+            b => Some(b),
+        }
+    }
+}
+
+impl<'a, C: Checksum> FusedIterator for EncodeIter<'a, C> {}
+
+#[cfg(test)]
+use crate::{encode_into_with, Crc16, NoCrc, ENCODED_MAX_LEN};
+#[cfg(all(test, feature = "std"))]
+use crate::{Encode, Packet, DATA_MAX_LEN};
+#[cfg(all(test, feature = "std"))]
+use rand::Rng;
+
+#[test]
+fn encode_iter_slice_test() {
+    // the same bytes as the slice encoder
+    let mut buf = [0u8; ENCODED_MAX_LEN];
+    let p = PacketRef {
+        address: Some(0x40),
+        command: 0x40,
+        data: &[FEND, FESC, 1],
+    };
+    let n = encode_into_with(p.address, p.command, p.data, &mut buf, &Config::new()).unwrap();
+    assert!(p.encode_iter().unwrap().eq(buf[..n].iter().copied()));
+    let crc16 = Config::<Crc16>::default();
+    let n = encode_into_with(p.address, p.command, p.data, &mut buf, &crc16).unwrap();
+    assert!(p
+        .encode_iter_with(&crc16)
+        .unwrap()
+        .eq(buf[..n].iter().copied()));
+    let no_crc = Config::<NoCrc>::default();
+    let n = encode_into_with(None, 3, &[], &mut buf, &no_crc).unwrap();
+    let empty = PacketRef {
+        command: 3,
+        ..Default::default()
+    };
+    assert!(empty
+        .encode_iter_with(&no_crc)
+        .unwrap()
+        .eq(buf[..n].iter().copied()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn encode_iter_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![FEND, FESC, 1])
+        .build()
+        .unwrap();
+    let encoded: Vec<u8> = wp.encode_iter().unwrap().collect();
+    assert_eq!(encoded, wp.encode().unwrap());
+    let mut iter = wp.encode_iter().unwrap();
+    assert_eq!(iter.by_ref().count(), encoded.len());
+    assert_eq!(iter.next(), None);
+
+    let wrong = PacketRef {
+        address: Some(0x80),
+        ..Default::default()
+    };
+    assert_eq!(
+        wrong.encode_iter().err(),
+        Some(WakeError::WrongAddrRange { address: 0x80 })
+    );
+    let wrong = PacketRef {
+        command: 0x80,
+        ..Default::default()
+    };
+    assert_eq!(
+        wrong.encode_iter().err(),
+        Some(WakeError::WrongCmdRange { command: 0x80 })
+    );
+    let small = Config::new().with_max_data_len(2);
+    assert_eq!(
+        PacketRef::from(&wp).encode_iter_with(&small).err(),
+        Some(WakeError::TooLongData { len: 3, max: 2 })
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn random_encode_iter_test() {
+    let mut rng = rand::thread_rng();
+    let crc16 = Config::<Crc16>::default();
+    let no_crc = Config::<NoCrc>::default();
+
+    for _ in 0..10_000 {
+        let n = rng.gen_range(0..=DATA_MAX_LEN);
+        let mut builder = Packet::builder()
+            .command(rng.gen_range(0..0x80))
+            .payload((0..n).map(|_| rng.gen()).collect::<Vec<u8>>());
+        if rng.gen_bool(0.5) {
+            builder = builder.address(rng.gen_range(0..0x80));
+        }
+        let wp = builder.build().unwrap();
+        let p = PacketRef::from(&wp);
+        assert!(p.encode_iter().unwrap().eq(wp.encode().unwrap()));
+        assert!(p
+            .encode_iter_with(&crc16)
+            .unwrap()
+            .eq(wp.encode_with(&crc16).unwrap()));
+        assert!(p
+            .encode_iter_with(&no_crc)
+            .unwrap()
+            .eq(wp.encode_with(&no_crc).unwrap()));
+    }
+}
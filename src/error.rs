@@ -0,0 +1,281 @@
+//! Decoder/encoder errors with the context of the failure
+
+use core::fmt;
+
+/// Wake decoder/encoder errors
+///
+/// Offsets are counted from the start symbol (FEND) of the frame, in received (stuffed) bytes.
+/// Use [`WakeError::kind`] to match errors without their context.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WakeError {
+    /// Frame ends before its header is complete
+    TooShortPacket {
+        /// Received frame length
+        len: usize,
+    },
+    /// Frame does not begin with FEND
+    CannotFindStart {
+        /// First byte of the frame
+        found: u8,
+    },
+    /// FESC is followed by a byte other than TFEND or TFESC
+    DestuffingFailed {
+        /// Offset of the byte after FESC
+        offset: usize,
+        /// Byte after FESC
+        byte: u8,
+    },
+    /// Data length differs from the one declared in the header
+    WrongPacketLength {
+        /// Data length declared in the header
+        declared: usize,
+        /// Received data length
+        actual: usize,
+    },
+    /// Received CRC differs from the calculated one
+    WrongPacketCrc {
+        /// Calculated CRC
+        expected: u16,
+        /// Received CRC
+        received: u16,
+    },
+    /// Address is above 127
+    WrongAddrRange {
+        /// Wrong address
+        address: u8,
+    },
+    /// Command is above 127
+    WrongCmdRange {
+        /// Wrong command
+        command: u8,
+    },
+    /// Output buffer is too small
+    BufferTooSmall {
+        /// Needed length
+        needed: usize,
+        /// Buffer length
+        available: usize,
+    },
+    /// Data is longer than the link configuration allows
+    TooLongData {
+        /// Data length
+        len: usize,
+        /// Maximum data length
+        max: usize,
+    },
+}
+
+/// Kind of [`WakeError`], without the context
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum WakeErrorKind {
+    TooShortPacket,
+    CannotFindStart,
+    DestuffingFailed,
+    WrongPacketLength,
+    WrongPacketCrc,
+    WrongAddrRange,
+    WrongCmdRange,
+    BufferTooSmall,
+    TooLongData,
+}
+
+impl WakeError {
+    /// Error kind, e.g. to match errors regardless of their context
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate wake_rs;
+    /// use wake_rs::{PacketRef, WakeErrorKind};
+    ///
+    /// let mut frame = [0xC0, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6c];
+    /// let err = PacketRef::decode(&mut frame).unwrap_err();
+    /// assert_eq!(err.kind(), WakeErrorKind::WrongPacketCrc);
+    /// assert_eq!(err.to_string(), "wrong packet CRC: expected 0x6B, received 0x6C");
+    /// ```
+    pub fn kind(&self) -> WakeErrorKind {
+        match self {
+            WakeError::TooShortPacket { .. } => WakeErrorKind::TooShortPacket,
+            WakeError::CannotFindStart { .. } => WakeErrorKind::CannotFindStart,
+            WakeError::DestuffingFailed { .. } => WakeErrorKind::DestuffingFailed,
+            WakeError::WrongPacketLength { .. } => WakeErrorKind::WrongPacketLength,
+            WakeError::WrongPacketCrc { .. } => WakeErrorKind::WrongPacketCrc,
+            WakeError::WrongAddrRange { .. } => WakeErrorKind::WrongAddrRange,
+            WakeError::WrongCmdRange { .. } => WakeErrorKind::WrongCmdRange,
+            WakeError::BufferTooSmall { .. } => WakeErrorKind::BufferTooSmall,
+            WakeError::TooLongData { .. } => WakeErrorKind::TooLongData,
+        }
+    }
+}
+
+impl fmt::Display for WakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WakeError::TooShortPacket { len } => {
+                write!(f, "too short packet: {} bytes", len)
+            }
+            WakeError::CannotFindStart { found } => {
+                write!(f, "can't find a start of the packet: 0x{:02X}", found)
+            }
+            WakeError::DestuffingFailed { offset, byte } => write!(
+                f,
+                "de-stuffing failed: 0x{:02X} after FESC at offset {}",
+                byte, offset
+            ),
+            WakeError::WrongPacketLength { declared, actual } => write!(
+                f,
+                "wrong packet length: {} data bytes declared, {} received",
+                declared, actual
+            ),
+            WakeError::WrongPacketCrc { expected, received } => write!(
+                f,
+                "wrong packet CRC: expected 0x{:02X}, received 0x{:02X}",
+                expected, received
+            ),
+            WakeError::WrongAddrRange { address } => {
+                write!(f, "address 0x{:02X} is out of range [0 - 127]", address)
+            }
+            WakeError::WrongCmdRange { command } => {
+                write!(f, "command 0x{:02X} is out of range [0 - 127]", command)
+            }
+            WakeError::BufferTooSmall { needed, available } => write!(
+                f,
+                "output buffer is too small: {} bytes needed, {} available",
+                needed, available
+            ),
+            WakeError::TooLongData { len, max } => {
+                write!(f, "data is longer than allowed: {} bytes, {} max", len, max)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WakeError {}
+
+/// Error of packet readers, writers and codecs over an I/O stream
+///
+/// Broken frames and packets which can't be encoded are reported as [`StreamError::Wake`],
+/// reading or writing can go on after them. [`StreamError::Io`] is the error of the underlying
+/// stream as it is.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StreamError {
+    /// Broken frame or a packet which can't be encoded
+    Wake(WakeError),
+    /// I/O error of the underlying reader or writer
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl StreamError {
+    /// Kind of the I/O error, `None` for a Wake error
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate wake_rs;
+    /// use std::io::ErrorKind;
+    /// use wake_rs::PacketReader;
+    ///
+    /// let mut reader = PacketReader::new(&[][..]);
+    /// let e = reader.read_packet().unwrap_err();
+    /// assert_eq!(e.io_kind(), Some(ErrorKind::UnexpectedEof));
+    /// ```
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        match self {
+            StreamError::Wake(_) => None,
+            StreamError::Io(e) => Some(e.kind()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Wake(e) => e.fmt(f),
+            StreamError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Wake(e) => Some(e),
+            StreamError::Io(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<WakeError> for StreamError {
+    fn from(e: WakeError) -> Self {
+        StreamError::Wake(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for StreamError {
+    fn from(e: std::io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+impl fmt::Display for WakeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WakeErrorKind::TooShortPacket => "too short packet",
+            WakeErrorKind::CannotFindStart => "can't find a start of the packet",
+            WakeErrorKind::DestuffingFailed => "de-stuffing failed",
+            WakeErrorKind::WrongPacketLength => "wrong packet length",
+            WakeErrorKind::WrongPacketCrc => "wrong packet CRC",
+            WakeErrorKind::WrongAddrRange => "address is out of range [0 - 127]",
+            WakeErrorKind::WrongCmdRange => "command is out of range [0 - 127]",
+            WakeErrorKind::BufferTooSmall => "output buffer is too small",
+            WakeErrorKind::TooLongData => "data is longer than allowed",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn error_display_test() {
+    let e = WakeError::DestuffingFailed {
+        offset: 3,
+        byte: 0x12,
+    };
+    assert_eq!(e.kind(), WakeErrorKind::DestuffingFailed);
+    assert_eq!(
+        e.to_string(),
+        "de-stuffing failed: 0x12 after FESC at offset 3"
+    );
+    let e = WakeError::WrongPacketLength {
+        declared: 5,
+        actual: 4,
+    };
+    assert_eq!(
+        e.to_string(),
+        "wrong packet length: 5 data bytes declared, 4 received"
+    );
+    assert_eq!(
+        WakeErrorKind::WrongPacketLength.to_string(),
+        "wrong packet length"
+    );
+    let e = StreamError::from(std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        "no reply from the device",
+    ));
+    assert_eq!(e.io_kind(), Some(std::io::ErrorKind::TimedOut));
+    assert_eq!(e.to_string(), "I/O error: no reply from the device");
+    let e = StreamError::from(WakeError::TooShortPacket { len: 2 });
+    assert_eq!(e.io_kind(), None);
+    assert_eq!(e.to_string(), "too short packet: 2 bytes");
+}
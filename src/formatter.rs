@@ -0,0 +1,470 @@
+//! Configurable human readable output of packets
+
+use core::{any, fmt, marker::PhantomData};
+
+use crate::{Checksum, Crc8, PacketRef, ADDR_MASK, FEND};
+
+const COLOR_ADDRESS: &str = "\x1b[33m";
+const COLOR_COMMAND: &str = "\x1b[36m";
+const COLOR_CRC: &str = "\x1b[35m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Catalog shown instead of the installed one, there are no catalogs without `std`
+#[cfg(feature = "std")]
+type Catalog<'a> = Option<&'a crate::CommandCatalog>;
+#[cfg(not(feature = "std"))]
+type Catalog<'a> = Option<&'a ()>;
+
+/// Hex dump settings, the default is the `Display` output of packets
+///
+/// The checksum type parameter is only used to show the CRC (CRC-8 by default).
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{PacketFormatter, PacketRef};
+///
+/// let p = PacketRef { address: Some(0x12), command: 3, data: b"OK" };
+/// let compact = PacketFormatter::new().with_compact(true).with_ascii(true).with_crc(true);
+/// assert_eq!(
+///     compact.display(p).to_string(),
+///     "ADDR: 0x12 CMD: 0x03 DATA: 4f 4b |OK| CRC: 0x5E"
+/// );
+/// assert_eq!(PacketFormatter::new().display(p).to_string(), p.to_string());
+/// ```
+pub struct PacketFormatter<C = Crc8> {
+    bytes_per_row: usize,
+    ascii: bool,
+    compact: bool,
+    uppercase: bool,
+    colors: bool,
+    crc: bool,
+    names: bool,
+    checksum: PhantomData<C>,
+}
+
+impl PacketFormatter {
+    /// Default settings: 16 bytes per row, lower case, no ASCII column, no colors, no CRC,
+    /// command names are shown
+    pub const fn new() -> Self {
+        PacketFormatter {
+            bytes_per_row: 16,
+            ascii: false,
+            compact: false,
+            uppercase: false,
+            colors: false,
+            crc: false,
+            names: true,
+            checksum: PhantomData,
+        }
+    }
+}
+
+impl<C: Checksum> PacketFormatter<C> {
+    /// Set number of data bytes per row [1 - 255]
+    pub fn with_bytes_per_row(mut self, n: usize) -> Self {
+        self.bytes_per_row = n.clamp(1, 0xff);
+        self
+    }
+
+    /// Show printable data bytes as text next to the hex dump
+    pub fn with_ascii(mut self, on: bool) -> Self {
+        self.ascii = on;
+        self
+    }
+
+    /// Write everything in a single line without the trailing newline, e.g. for logs
+    pub fn with_compact(mut self, on: bool) -> Self {
+        self.compact = on;
+        self
+    }
+
+    /// Upper case hex digits in the data dump, address and command are always `0x7F`
+    pub fn with_uppercase(mut self, on: bool) -> Self {
+        self.uppercase = on;
+        self
+    }
+
+    /// Highlight address, command and CRC values with ANSI colors
+    pub fn with_colors(mut self, on: bool) -> Self {
+        self.colors = on;
+        self
+    }
+
+    /// Show the checksum the packet is sent with, nothing is shown for `NoCrc`
+    pub fn with_crc(mut self, on: bool) -> Self {
+        self.crc = on;
+        self
+    }
+
+    /// Show command names and decoded payloads of the installed
+    /// [`CommandCatalog`](crate::CommandCatalog), on by default
+    #[cfg(feature = "std")]
+    pub fn with_names(mut self, on: bool) -> Self {
+        self.names = on;
+        self
+    }
+
+    /// Use another checksum for the CRC, the other settings are kept
+    pub fn with_checksum<D: Checksum>(self) -> PacketFormatter<D> {
+        PacketFormatter {
+            bytes_per_row: self.bytes_per_row,
+            ascii: self.ascii,
+            compact: self.compact,
+            uppercase: self.uppercase,
+            colors: self.colors,
+            crc: self.crc,
+            names: self.names,
+            checksum: PhantomData,
+        }
+    }
+
+    /// Wrap a packet to be shown with these settings by `{}`
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - `PacketRef`, `&Packet` or `&HeaplessPacket`
+    ///
+    /// # Output
+    ///
+    /// * `PacketDisplay` - value implementing `Display`
+    ///
+    pub fn display<'a>(&self, packet: impl Into<PacketRef<'a>>) -> PacketDisplay<'a, C> {
+        PacketDisplay {
+            formatter: *self,
+            packet: packet.into(),
+            catalog: None,
+        }
+    }
+
+    /// Wrap a packet to be shown with command names of the given catalog instead of the
+    /// installed one, see [`PacketFormatter::display`]
+    #[cfg(feature = "std")]
+    pub fn display_with_catalog<'a>(
+        &self,
+        packet: impl Into<PacketRef<'a>>,
+        catalog: &'a crate::CommandCatalog,
+    ) -> PacketDisplay<'a, C> {
+        PacketDisplay {
+            formatter: *self,
+            packet: packet.into(),
+            catalog: Some(catalog),
+        }
+    }
+
+    /// Write packet fields, `None` data is shown as `none`
+    pub(crate) fn write(
+        &self,
+        f: &mut fmt::Formatter,
+        catalog: Catalog,
+        address: Option<u8>,
+        command: u8,
+        data: Option<&[u8]>,
+    ) -> fmt::Result {
+        // 1: Header
+        let separator = if self.compact { " " } else { "\n" };
+        f.write_str("ADDR: ")?;
+        match address {
+            Some(a) => self.write_colored(f, COLOR_ADDRESS, format_args!("0x{:02X}", a))?,
+            None => f.write_str("----")?,
+        };
+        f.write_str(separator)?;
+        f.write_str(if self.compact { "CMD: " } else { "CMD:  " })?;
+        self.write_colored(f, COLOR_COMMAND, format_args!("0x{:02X}", command))?;
+        let decoded = self.write_name(f, catalog, address, command, data.unwrap_or_default())?;
+
+        // 2: Data, unless the catalog decoded it
+        if !decoded {
+            f.write_str(separator)?;
+        }
+        match data {
+            _ if decoded => {}
+            Some(d) if self.compact => {
+                f.write_str("DATA:")?;
+                for byte in d {
+                    f.write_str(" ")?;
+                    self.write_hex(f, *byte)?;
+                }
+                if self.ascii {
+                    f.write_str(" ")?;
+                    write_ascii(f, d)?;
+                }
+            }
+            Some(d) => {
+                // offsets are as wide as the last one, 2 digits at least
+                let last = d.len().saturating_sub(1) / self.bytes_per_row * self.bytes_per_row;
+                let width = offset_width(last);
+                write!(
+                    f,
+                    "DATA: {} bytes\n{:width$}",
+                    d.len(),
+                    "",
+                    width = width + 1
+                )?;
+                for i in 0..self.bytes_per_row {
+                    if self.uppercase {
+                        write!(f, " {:>2X}", i)?;
+                    } else {
+                        write!(f, " {:>2x}", i)?;
+                    }
+                }
+                for (i, row) in d.chunks(self.bytes_per_row).enumerate() {
+                    f.write_str("\n")?;
+                    self.write_offset(f, i * self.bytes_per_row, width)?;
+                    f.write_str(": ")?;
+                    for byte in row {
+                        self.write_hex(f, *byte)?;
+                        f.write_str(" ")?;
+                    }
+                    if self.ascii {
+                        for _ in row.len()..self.bytes_per_row {
+                            f.write_str("   ")?;
+                        }
+                        write_ascii(f, row)?;
+                    }
+                }
+            }
+            None => f.write_str("DATA: none")?,
+        };
+
+        // 3: Checksum
+        if self.crc && C::LEN > 0 {
+            f.write_str(separator)?;
+            f.write_str(if self.compact { "CRC: " } else { "CRC:  " })?;
+            let crc = frame_checksum::<C>(address, command, data.unwrap_or_default());
+            match C::LEN {
+                1 => self.write_colored(f, COLOR_CRC, format_args!("0x{:02X}", crc))?,
+                _ => self.write_colored(f, COLOR_CRC, format_args!("0x{:04X}", crc))?,
+            }
+        }
+        if self.compact {
+            Ok(())
+        } else {
+            writeln!(f)
+        }
+    }
+
+    /// Command name and decoded payload, see [`CommandCatalog`](crate::CommandCatalog)
+    #[cfg(feature = "std")]
+    fn write_name(
+        &self,
+        f: &mut fmt::Formatter,
+        catalog: Catalog,
+        address: Option<u8>,
+        command: u8,
+        data: &[u8],
+    ) -> Result<bool, fmt::Error> {
+        match catalog {
+            _ if !self.names => Ok(false),
+            Some(catalog) => catalog.write_name(f, address, command, data),
+            None => crate::catalog::write_global_name(f, address, command, data),
+        }
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn write_name(
+        &self,
+        _f: &mut fmt::Formatter,
+        _catalog: Catalog,
+        _address: Option<u8>,
+        _command: u8,
+        _data: &[u8],
+    ) -> Result<bool, fmt::Error> {
+        Ok(false)
+    }
+
+    fn write_hex(&self, f: &mut fmt::Formatter, byte: u8) -> fmt::Result {
+        if self.uppercase {
+            write!(f, "{:02X}", byte)
+        } else {
+            write!(f, "{:02x}", byte)
+        }
+    }
+
+    fn write_offset(&self, f: &mut fmt::Formatter, offset: usize, width: usize) -> fmt::Result {
+        if self.uppercase {
+            write!(f, "{:0width$X}", offset, width = width)
+        } else {
+            write!(f, "{:0width$x}", offset, width = width)
+        }
+    }
+
+    fn write_colored(
+        &self,
+        f: &mut fmt::Formatter,
+        color: &str,
+        value: fmt::Arguments,
+    ) -> fmt::Result {
+        if self.colors {
+            write!(f, "{}{}{}", color, value, COLOR_RESET)
+        } else {
+            f.write_fmt(value)
+        }
+    }
+}
+
+/// Printable bytes as is, the others as `.`, e.g. `|OK.|`
+fn write_ascii(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    f.write_str("|")?;
+    for byte in data {
+        if byte.is_ascii_graphic() || *byte == b' ' {
+            write!(f, "{}", *byte as char)?;
+        } else {
+            f.write_str(".")?;
+        }
+    }
+    f.write_str("|")
+}
+
+/// Checksum of the destuffed frame as the encoder calculates it
+fn frame_checksum<C: Checksum>(address: Option<u8>, command: u8, data: &[u8]) -> u16 {
+    let mut crc = C::default();
+    crc.update(FEND);
+    if let Some(a) = address {
+        crc.update(a | ADDR_MASK);
+    }
+    crc.update(command);
+    crc.update(data.len() as u8);
+    for byte in data {
+        crc.update(*byte);
+    }
+    crc.value()
+}
+
+impl<C: Checksum> Default for PacketFormatter<C> {
+    fn default() -> Self {
+        PacketFormatter::new().with_checksum()
+    }
+}
+
+impl<C> Clone for PacketFormatter<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for PacketFormatter<C> {}
+
+impl<C> fmt::Debug for PacketFormatter<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PacketFormatter")
+            .field("bytes_per_row", &self.bytes_per_row)
+            .field("ascii", &self.ascii)
+            .field("compact", &self.compact)
+            .field("uppercase", &self.uppercase)
+            .field("colors", &self.colors)
+            .field("crc", &self.crc)
+            .field("names", &self.names)
+            .field("checksum", &any::type_name::<C>())
+            .finish()
+    }
+}
+
+/// Packet shown with [`PacketFormatter`] settings, see [`PacketFormatter::display`]
+#[derive(Clone, Copy, Debug)]
+pub struct PacketDisplay<'a, C = Crc8> {
+    formatter: PacketFormatter<C>,
+    packet: PacketRef<'a>,
+    catalog: Catalog<'a>,
+}
+
+impl<'a, C: Checksum> fmt::Display for PacketDisplay<'a, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = match self.packet.data.len() {
+            0 => None,
+            _ => Some(self.packet.data),
+        };
+        let p = &self.packet;
+        self.formatter
+            .write(f, self.catalog, p.address, p.command, data)
+    }
+}
+
+/// Number of hex digits of the row offset, at least 2
+fn offset_width(offset: usize) -> usize {
+    let mut width = 2;
+    while width < usize::BITS as usize / 4 && offset >> (4 * width) != 0 {
+        width += 1;
+    }
+    width
+}
+
+#[cfg(all(test, feature = "std"))]
+use crate::{Crc16, Encode, NoCrc};
+
+#[cfg(feature = "std")]
+#[test]
+fn packet_formatter_test() {
+    let p = PacketRef {
+        address: Some(0x12),
+        command: 3,
+        data: b"Relay\x00\x01",
+    };
+    let rows = PacketFormatter::new()
+        .with_bytes_per_row(4)
+        .with_ascii(true)
+        .with_uppercase(true);
+    assert_eq!(
+        rows.display(p).to_string(),
+        "ADDR: 0x12\nCMD:  0x03\nDATA: 7 bytes\n     \
+         0  1  2  3\n\
+         00: 52 65 6C 61 |Rela|\n\
+         04: 79 00 01    |y..|\n"
+    );
+    let compact = PacketFormatter::new().with_compact(true);
+    assert_eq!(
+        compact.display(p).to_string(),
+        "ADDR: 0x12 CMD: 0x03 DATA: 52 65 6c 61 79 00 01"
+    );
+    let empty = PacketRef {
+        command: 0x7f,
+        ..Default::default()
+    };
+    assert_eq!(
+        compact.display(empty).to_string(),
+        "ADDR: ---- CMD: 0x7F DATA: none"
+    );
+    // the CRC is the encoded trailer
+    let encoded = p.encode().unwrap();
+    let crc = format!("CRC:  0x{:02X}\n", encoded.last().unwrap());
+    let text = PacketFormatter::new().with_crc(true).display(p).to_string();
+    assert!(text.ends_with(&crc), "{}", text);
+    let crc16 = PacketFormatter::new()
+        .with_crc(true)
+        .with_compact(true)
+        .with_checksum::<Crc16>();
+    let encoded = p.encode_with(&crate::Config::<Crc16>::default()).unwrap();
+    let trailer = &encoded[encoded.len() - 2..];
+    assert!(crc16.display(empty).to_string().contains("CRC: 0x"));
+    assert!(crc16
+        .display(p)
+        .to_string()
+        .ends_with(&format!("CRC: 0x{:02X}{:02X}", trailer[0], trailer[1])));
+    let no_crc = compact.with_crc(true).with_checksum::<NoCrc>();
+    assert!(!no_crc.display(p).to_string().contains("CRC"));
+    // offsets of long data don't wrap, the header is aligned with the rows
+    let long = [0xAA; 300];
+    let long = PacketRef {
+        data: &long,
+        ..Default::default()
+    };
+    let text = PacketFormatter::new().display(long).to_string();
+    let rows: Vec<&str> = text.lines().collect();
+    assert!(rows[3].starts_with("      0  1"), "{}", rows[3]);
+    assert_eq!(rows[4], format!("000: {}", "aa ".repeat(16)));
+    assert_eq!(rows[22], format!("120: {}", "aa ".repeat(12)));
+    let wide = PacketFormatter::new().with_bytes_per_row(1000);
+    let text = wide.display(long).to_string();
+    let rows: Vec<&str> = text.lines().collect();
+    assert!(rows[3].ends_with(" fd fe"), "{}", rows[3]);
+    assert_eq!(rows[4].len(), rows[3].len() + 1);
+    assert!(rows[5].starts_with("ff: aa"), "{}", rows[5]);
+    assert_eq!(offset_width(0), 2);
+    assert_eq!(offset_width(0xff), 2);
+    assert_eq!(offset_width(0x100), 3);
+    assert_eq!(offset_width(usize::MAX), usize::BITS as usize / 4);
+    // colors
+    let colored = compact.with_colors(true).display(empty).to_string();
+    assert_eq!(colored, "ADDR: ---- CMD: \x1b[36m0x7F\x1b[0m DATA: none");
+}
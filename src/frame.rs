@@ -0,0 +1,790 @@
+//! Allocation-free encoder/decoder working on slices. Available without `std`.
+
+use crate::{
+    Checksum, Command, Config, WakeError, ADDR_MASK, DATA_MAX_LEN, FEND, FESC, PACKET_MIN_LEN,
+    TFEND, TFESC,
+};
+
+/// Decoded packet header, the payload is stored into a caller-supplied buffer
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Header {
+    /// Device address (optional) [0 - 127]
+    pub address: Option<u8>,
+    /// Command [0 - 127]
+    pub command: u8,
+    /// Payload length
+    pub len: usize,
+}
+
+/// Destination of encoded bytes
+trait Output {
+    fn write(&mut self, byte: u8) -> Result<(), WakeError>;
+}
+
+/// Encoded bytes go into a slice
+struct SliceOutput<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Output for SliceOutput<'a> {
+    fn write(&mut self, byte: u8) -> Result<(), WakeError> {
+        // the needed length is filled in by `encode_into_with`
+        let available = self.buf.len();
+        let slot = self
+            .buf
+            .get_mut(self.pos)
+            .ok_or(WakeError::BufferTooSmall {
+                needed: 0,
+                available,
+            })?;
+        *slot = byte;
+        self.pos += 1;
+        Ok(())
+    }
+}
+
+/// Encoded bytes are only counted
+struct Counter(usize);
+
+impl Output for Counter {
+    fn write(&mut self, _byte: u8) -> Result<(), WakeError> {
+        self.0 += 1;
+        Ok(())
+    }
+}
+
+/// Encoded bytes are appended to a vector
+#[cfg(feature = "std")]
+impl Output for Vec<u8> {
+    fn write(&mut self, byte: u8) -> Result<(), WakeError> {
+        self.push(byte);
+        Ok(())
+    }
+}
+
+/// Stuffs bytes into an output and keeps a running CRC
+struct Writer<'o, O, C> {
+    out: &'o mut O,
+    crc: C,
+}
+
+impl<'o, O: Output, C: Checksum> Writer<'o, O, C> {
+    fn stuff(&mut self, byte: u8) -> Result<(), WakeError> {
+        match byte {
+            FEND => {
+                self.out.write(FESC)?;
+                self.out.write(TFEND)
+            }
+            FESC => {
+                self.out.write(FESC)?;
+                self.out.write(TFESC)
+            }
+            _ => self.out.write(byte),
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), WakeError> {
+        self.crc.update(byte);
+        self.stuff(byte)
+    }
+
+    fn trailer(&mut self) -> Result<(), WakeError> {
+        let value = self.crc.value().to_be_bytes();
+        for b in &value[value.len().saturating_sub(C::LEN)..] {
+            self.stuff(*b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encode one frame, calculating the CRC and stuffing bytes in a single pass
+fn encode_frame<O: Output, C: Checksum>(
+    out: &mut O,
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+    config: &Config<C>,
+) -> Result<(), WakeError> {
+    let mut w = Writer {
+        out,
+        crc: C::default(),
+    };
+    // 1. FEND
+    w.crc.update(FEND);
+    w.out.write(FEND)?;
+    // 2. Address, if exists
+    if let Some(addr) = address {
+        if addr > 0x7f {
+            return Err(WakeError::WrongAddrRange { address: addr });
+        }
+        w.push(addr | ADDR_MASK)?;
+    }
+    // 3. Command
+    if command > 0x7f {
+        return Err(WakeError::WrongCmdRange { command });
+    }
+    w.push(command)?;
+    // 4. Data length; data
+    if data.len() > config.max_data_len() {
+        return Err(WakeError::TooLongData {
+            len: data.len(),
+            max: config.max_data_len(),
+        });
+    }
+    w.push(data.len() as u8)?;
+    for b in data {
+        w.push(*b)?;
+    }
+    // 5. CRC
+    w.trailer()
+}
+
+/// Encoded frame being decoded, keeps destuffed data where the caller wants it
+trait Input {
+    /// Bytes are stuffed, `false` if the frame has been destuffed already
+    const STUFFED: bool = true;
+    /// Encoded byte at `pos`
+    fn get(&self, pos: usize) -> Option<u8>;
+    /// Encoded frame length
+    fn end(&self) -> usize;
+    /// Maximum data length which can be stored
+    fn capacity(&self) -> usize;
+    /// Data begins at the encoded byte `pos`
+    fn start_data(&mut self, _pos: usize) {}
+    /// Store data byte number `n`, it is always read before it is stored
+    fn store(&mut self, n: usize, byte: u8);
+}
+
+/// Encoded frame in a slice, data is copied into another slice
+struct CopyInput<'a, 'b> {
+    frame: &'a [u8],
+    data: &'b mut [u8],
+}
+
+impl<'a, 'b> Input for CopyInput<'a, 'b> {
+    fn get(&self, pos: usize) -> Option<u8> {
+        self.frame.get(pos).copied()
+    }
+
+    fn end(&self) -> usize {
+        self.frame.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn store(&mut self, n: usize, byte: u8) {
+        if let Some(d) = self.data.get_mut(n) {
+            *d = byte;
+        }
+    }
+}
+
+/// Encoded frame destuffed in place, data is kept in `buf[start..]`
+///
+/// Destuffing never makes data longer, so data byte `n` is written over the encoded
+/// bytes which have been read already.
+struct InPlaceInput<'a> {
+    buf: &'a mut [u8],
+    start: usize,
+}
+
+impl<'a> Input for InPlaceInput<'a> {
+    fn get(&self, pos: usize) -> Option<u8> {
+        self.buf.get(pos).copied()
+    }
+
+    fn end(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn capacity(&self) -> usize {
+        DATA_MAX_LEN
+    }
+
+    fn start_data(&mut self, pos: usize) {
+        self.start = pos;
+    }
+
+    fn store(&mut self, n: usize, byte: u8) {
+        if let Some(d) = self.buf.get_mut(self.start + n) {
+            *d = byte;
+        }
+    }
+}
+
+/// Frame destuffed by the stream decoder, data is kept where it is
+struct DestuffedInput<'a> {
+    frame: &'a [u8],
+    start: usize,
+}
+
+impl<'a> Input for DestuffedInput<'a> {
+    const STUFFED: bool = false;
+
+    fn get(&self, pos: usize) -> Option<u8> {
+        self.frame.get(pos).copied()
+    }
+
+    fn end(&self) -> usize {
+        self.frame.len()
+    }
+
+    fn capacity(&self) -> usize {
+        DATA_MAX_LEN
+    }
+
+    fn start_data(&mut self, pos: usize) {
+        self.start = pos;
+    }
+
+    fn store(&mut self, _n: usize, _byte: u8) {}
+}
+
+/// Destuffs bytes from an encoded frame and keeps a running CRC
+struct Reader<'r, I, C> {
+    input: &'r mut I,
+    pos: usize,
+    count: usize,
+    crc: C,
+}
+
+impl<'r, I: Input, C: Checksum> Reader<'r, I, C> {
+    /// Get the next destuffed byte, `None` at the end of the frame
+    fn pull(&mut self) -> Result<Option<u8>, WakeError> {
+        let (byte, len) = match self.input.get(self.pos) {
+            Some(FESC) if I::STUFFED => match self.input.get(self.pos + 1) {
+                Some(TFEND) => (FEND, 2),
+                Some(TFESC) => (FESC, 2),
+                Some(byte) => {
+                    return Err(WakeError::DestuffingFailed {
+                        offset: self.pos + 1,
+                        byte,
+                    })
+                }
+                None => return Ok(None),
+            },
+            Some(b) => (b, 1),
+            None => return Ok(None),
+        };
+        self.pos += len;
+        self.count += 1;
+        self.crc.update(byte);
+        Ok(Some(byte))
+    }
+
+    /// Get the checksum trailer, `None` if the frame ends before it
+    fn trailer(&mut self) -> Result<Option<u16>, WakeError> {
+        let mut received = 0u16;
+        for _ in 0..C::LEN {
+            match self.pull()? {
+                Some(b) => received = received << 8 | b as u16,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(received))
+    }
+
+    /// Data length mismatch, `header_len` destuffed bytes precede the data
+    fn wrong_len(&self, declared: usize, header_len: usize) -> WakeError {
+        // count the rest of the frame without checking it
+        let mut total = self.count;
+        let mut pos = self.pos;
+        while pos < self.input.end() {
+            pos += if I::STUFFED && self.input.get(pos) == Some(FESC) {
+                2
+            } else {
+                1
+            };
+            total += 1;
+        }
+        WakeError::WrongPacketLength {
+            declared,
+            actual: total.saturating_sub(header_len + C::LEN),
+        }
+    }
+}
+
+/// Decode one encoded frame, destuffing and checking the CRC in a single pass
+fn decode_frame<I: Input, C: Checksum>(
+    input: &mut I,
+    config: &Config<C>,
+) -> Result<Header, WakeError> {
+    // 1: Check packet length
+    let end = input.end();
+    let too_short = WakeError::TooShortPacket { len: end };
+    if end < PACKET_MIN_LEN + C::LEN {
+        return Err(too_short);
+    }
+    // 2: Check START symbol (FEND)
+    match input.get(0) {
+        Some(FEND) => (),
+        found => {
+            return Err(WakeError::CannotFindStart {
+                found: found.unwrap_or_default(),
+            })
+        }
+    }
+    // 3: Dry the rest of the packet on the fly
+    let mut r = Reader {
+        input,
+        pos: 1,
+        count: 1,
+        crc: C::default(),
+    };
+    r.crc.update(FEND);
+    // 4: Get an address (if exists) and a command
+    let mut header = Header::default();
+    match r.pull()?.ok_or(too_short)? {
+        addr @ ADDR_MASK..=0xff => {
+            header.address = Some(addr & !ADDR_MASK);
+            header.command = r.pull()?.ok_or(too_short)?;
+        }
+        cmd => header.command = cmd,
+    }
+    Command::new(header.command)?;
+    // 5: Get data length
+    header.len = r.pull()?.ok_or(too_short)? as usize;
+    if header.len > config.max_data_len() {
+        return Err(WakeError::TooLongData {
+            len: header.len,
+            max: config.max_data_len(),
+        });
+    }
+    if header.len > r.input.capacity() {
+        return Err(WakeError::BufferTooSmall {
+            needed: header.len,
+            available: r.input.capacity(),
+        });
+    }
+    let header_len = r.count;
+    // 6: Get data
+    r.input.start_data(r.pos);
+    for n in 0..header.len {
+        match r.pull()? {
+            Some(byte) => r.input.store(n, byte),
+            None => return Err(r.wrong_len(header.len, header_len)),
+        }
+    }
+    // 7: Get CRC, it must be the last byte
+    let expected = r.crc.value();
+    let received = match r.trailer()? {
+        Some(received) if r.pos == end => received,
+        _ => return Err(r.wrong_len(header.len, header_len)),
+    };
+    // 8: Check CRC
+    if received != expected {
+        Err(WakeError::WrongPacketCrc { expected, received })
+    } else {
+        Ok(header)
+    }
+}
+
+/// Encode packet fields to wake format into a slice
+///
+/// # Arguments
+///
+/// * `address` - device address (optional) [0 - 127]
+/// * `command` - command [0 - 127]
+/// * `data` - data load, might be empty
+/// * `buf` - output buffer, `ENCODED_MAX_LEN` bytes fit any packet
+///
+/// # Output
+///
+/// * `Result<usize, WakeError>` - number of bytes written into `buf` or error.
+///   Data longer than `DATA_MAX_LEN` is rejected with `TooLongData`.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+///
+/// let mut buf = [0u8; 16];
+/// let n = wake_rs::encode_into(Some(0x12), 3, &[0x00, 0xeb], &mut buf).unwrap();
+/// assert_eq!(&buf[..n], &[0xC0, 0x92, 0x03, 0x02, 0x00, 0xeb, 114]);
+/// ```
+///
+pub fn encode_into(
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+    buf: &mut [u8],
+) -> Result<usize, WakeError> {
+    encode_into_with(address, command, data, buf, &Config::new())
+}
+
+/// Encode packet fields to wake format into a slice with the given link configuration, see [`encode_into`]
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{Config, Crc16, WakeError};
+///
+/// let config = Config::<Crc16>::default().with_max_data_len(2);
+/// let mut buf = [0u8; 16];
+/// let n = wake_rs::encode_into_with(None, 3, &[0x00, 0xeb], &mut buf, &config).unwrap();
+/// assert_eq!(n, 7); // FEND, CMD, N, 2 bytes of data, 2 bytes of CRC
+/// let too_long = wake_rs::encode_into_with(None, 3, &[1, 2, 3], &mut buf, &config);
+/// assert_eq!(too_long, Err(WakeError::TooLongData { len: 3, max: 2 }));
+/// ```
+///
+pub fn encode_into_with<C: Checksum>(
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+    buf: &mut [u8],
+    config: &Config<C>,
+) -> Result<usize, WakeError> {
+    let mut out = SliceOutput { buf, pos: 0 };
+    match encode_frame(&mut out, address, command, data, config) {
+        Ok(()) => Ok(out.pos),
+        Err(WakeError::BufferTooSmall { available, .. }) => {
+            // the rest of the packet might be wrong too
+            let needed = encoded_len_with(address, command, data, config)?;
+            Err(WakeError::BufferTooSmall { needed, available })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Exact length of the encoded frame, see [`encode_into`]
+///
+/// The CRC is calculated as well, since it might need stuffing.
+///
+/// # Output
+///
+/// * `Result<usize, WakeError>` - number of bytes `encode_into_with` writes or the error it returns
+///
+pub(crate) fn encoded_len_with<C: Checksum>(
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+    config: &Config<C>,
+) -> Result<usize, WakeError> {
+    let mut counter = Counter(0);
+    encode_frame(&mut counter, address, command, data, config)?;
+    Ok(counter.0)
+}
+
+/// Append an encoded frame to a vector, see [`encode_into`]
+///
+/// The vector is left unchanged on error.
+#[cfg(feature = "std")]
+pub(crate) fn encode_to_vec_with<C: Checksum>(
+    address: Option<u8>,
+    command: u8,
+    data: &[u8],
+    out: &mut Vec<u8>,
+    config: &Config<C>,
+) -> Result<usize, WakeError> {
+    let start = out.len();
+    // every byte after FEND is stuffed at most
+    out.reserve(1 + 2 * (3 + data.len().min(DATA_MAX_LEN) + C::LEN));
+    match encode_frame(out, address, command, data, config) {
+        Ok(()) => Ok(out.len() - start),
+        Err(e) => {
+            out.truncate(start);
+            Err(e)
+        }
+    }
+}
+
+/// Decode a wake frame from a slice
+///
+/// # Arguments
+///
+/// * `frame` - one encoded frame
+/// * `data` - output buffer for the data load, `DATA_MAX_LEN` bytes fit any frame
+///
+/// # Output
+///
+/// * `Result<Header, WakeError>` - address, command and data length or error
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+///
+/// let mut data = [0u8; wake_rs::DATA_MAX_LEN];
+/// let frame = [0xC0, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6b];
+/// let header = wake_rs::decode_into(&frame, &mut data).unwrap();
+/// assert_eq!(header.command, 3);
+/// assert_eq!(&data[..header.len], &[1, 2, 3, 4, 5]);
+/// ```
+///
+pub fn decode_into(frame: &[u8], data: &mut [u8]) -> Result<Header, WakeError> {
+    decode_into_with(frame, data, &Config::new())
+}
+
+/// Decode a wake frame from a slice with the given link configuration, see [`decode_into`]
+///
+/// Frames with more than `config.max_data_len()` bytes of data are rejected with `TooLongData`.
+pub fn decode_into_with<C: Checksum>(
+    frame: &[u8],
+    data: &mut [u8],
+    config: &Config<C>,
+) -> Result<Header, WakeError> {
+    decode_frame(&mut CopyInput { frame, data }, config)
+}
+
+/// Decode a wake frame in place, see [`PacketRef::decode`](crate::PacketRef::decode)
+///
+/// # Output
+///
+/// * `Result<(Header, &[u8]), WakeError>` - address, command, data length and data, which is
+///   destuffed into `buf`
+///
+pub(crate) fn decode_in_place_with<'a, C: Checksum>(
+    buf: &'a mut [u8],
+    config: &Config<C>,
+) -> Result<(Header, &'a [u8]), WakeError> {
+    let mut input = InPlaceInput { buf, start: 0 };
+    let header = decode_frame(&mut input, config)?;
+    let InPlaceInput { buf, start } = input;
+    let data = buf
+        .get(start..start + header.len)
+        .ok_or(WakeError::WrongPacketLength {
+            declared: header.len,
+            actual: 0,
+        })?;
+    Ok((header, data))
+}
+
+/// Decode a frame destuffed by [`StreamDecoder`](crate::StreamDecoder), the same checks as
+/// [`decode_into_with`] are made
+///
+/// # Output
+///
+/// * `Result<(Header, &[u8]), WakeError>` - address, command, data length and data, which is
+///   borrowed from `frame`
+///
+pub(crate) fn decode_destuffed_with<'a, C: Checksum>(
+    frame: &'a [u8],
+    config: &Config<C>,
+) -> Result<(Header, &'a [u8]), WakeError> {
+    let mut input = DestuffedInput { frame, start: 0 };
+    let header = decode_frame(&mut input, config)?;
+    let data =
+        frame
+            .get(input.start..input.start + header.len)
+            .ok_or(WakeError::WrongPacketLength {
+                declared: header.len,
+                actual: 0,
+            })?;
+    Ok((header, data))
+}
+
+#[cfg(test)]
+use crate::{Crc16, NoCrc, StreamDecoder};
+#[cfg(all(test, feature = "std"))]
+use crate::{Decode, Encode, PacketRef};
+#[cfg(test)]
+use rand::Rng;
+
+#[test]
+fn encode_into_test() {
+    let mut buf = [0u8; 8];
+    // empty packet with stuffing
+    assert_eq!(encode_into(Some(0x40), 0x40, &[], &mut buf), Ok(6));
+    assert_eq!(buf[..6], [FEND, FESC, TFEND, 0x40, 0x00, 229]);
+    // out of range
+    assert_eq!(
+        encode_into(Some(0x80), 0, &[], &mut buf),
+        Err(WakeError::WrongAddrRange { address: 0x80 })
+    );
+    assert_eq!(
+        encode_into(None, 0x80, &[], &mut buf),
+        Err(WakeError::WrongCmdRange { command: 0x80 })
+    );
+    // buffer is too small
+    assert_eq!(
+        encode_into(Some(0x40), 0x40, &[], &mut buf[..5]),
+        Err(WakeError::BufferTooSmall {
+            needed: 6,
+            available: 5
+        })
+    );
+    assert_eq!(
+        encode_into(None, 9, &[1, 2, 3, 4, 5], &mut buf),
+        Err(WakeError::BufferTooSmall {
+            needed: 9,
+            available: 8
+        })
+    );
+}
+
+#[test]
+fn decode_into_test() {
+    let mut data = [0u8; 4];
+    let frame = [FEND, FESC, TFEND, 0x40, 0x02, FESC, TFESC, 0x01, 0xe0];
+    let header = decode_into(&frame, &mut data).unwrap();
+    assert_eq!(header.address, Some(0x40));
+    assert_eq!(header.command, 0x40);
+    assert_eq!(data[..header.len], [FESC, 0x01]);
+    assert_eq!(
+        decode_into(&frame, &mut data[..1]),
+        Err(WakeError::BufferTooSmall {
+            needed: 2,
+            available: 1
+        })
+    );
+    assert_eq!(
+        decode_into(&frame[..frame.len() - 1], &mut data),
+        Err(WakeError::WrongPacketLength {
+            declared: 2,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        decode_into(&frame[..3], &mut data),
+        Err(WakeError::TooShortPacket { len: 3 })
+    );
+    assert_eq!(
+        decode_into(&frame[1..], &mut data),
+        Err(WakeError::CannotFindStart { found: FESC })
+    );
+    let bad = [FEND, 0x03, 0x01, FESC, 0x00, 0x00];
+    assert_eq!(
+        decode_into(&bad, &mut data),
+        Err(WakeError::DestuffingFailed {
+            offset: 4,
+            byte: 0x00
+        })
+    );
+}
+
+#[test]
+fn max_data_len_test() {
+    let mut buf = [0u8; crate::ENCODED_MAX_LEN];
+    let mut data = [0u8; DATA_MAX_LEN];
+    let long = [0u8; DATA_MAX_LEN + 1];
+    assert_eq!(
+        encode_into(None, 3, &long, &mut buf),
+        Err(WakeError::TooLongData { len: 256, max: 255 })
+    );
+    let small = Config::new().with_max_data_len(2);
+    assert_eq!(
+        encode_into_with(None, 3, &[1, 2, 3], &mut buf, &small),
+        Err(WakeError::TooLongData { len: 3, max: 2 })
+    );
+    let n = encode_into(None, 3, &[1, 2, 3], &mut buf).unwrap();
+    assert_eq!(
+        decode_into_with(&buf[..n], &mut data, &small),
+        Err(WakeError::TooLongData { len: 3, max: 2 })
+    );
+    let n = encode_into_with(None, 3, &[1, 2], &mut buf, &small).unwrap();
+    assert_eq!(
+        decode_into_with(&buf[..n], &mut data, &small).unwrap().len,
+        2
+    );
+}
+
+#[test]
+fn checksum_test() {
+    let mut buf = [0u8; 16];
+    let mut data = [0u8; 4];
+    let crc16 = Config::<Crc16>::default();
+    let no_crc = Config::<NoCrc>::default();
+    let n = encode_into_with(Some(0x12), 3, &[0x00, 0xeb], &mut buf, &crc16).unwrap();
+    let crc = Crc16::calculate(&[FEND, 0x92, 0x03, 0x02, 0x00, 0xeb]);
+    assert_eq!(buf[..n - 2], [FEND, 0x92, 0x03, 0x02, 0x00, 0xeb]);
+    assert_eq!(buf[n - 2..n], crc.to_be_bytes());
+    let header = decode_into_with(&buf[..n], &mut data, &crc16).unwrap();
+    assert_eq!(header.address, Some(0x12));
+    assert_eq!(data[..header.len], [0x00, 0xeb]);
+    assert_eq!(
+        decode_into(&buf[..n], &mut data),
+        Err(WakeError::WrongPacketLength {
+            declared: 2,
+            actual: 3
+        })
+    );
+    buf[n - 1] ^= 1;
+    assert_eq!(
+        decode_into_with(&buf[..n], &mut data, &crc16),
+        Err(WakeError::WrongPacketCrc {
+            expected: crc,
+            received: crc ^ 1
+        })
+    );
+
+    let n = encode_into_with(None, 3, &[], &mut buf, &no_crc).unwrap();
+    assert_eq!(buf[..n], [FEND, 0x03, 0x00]);
+    let header = decode_into_with(&buf[..n], &mut data, &no_crc).unwrap();
+    assert_eq!((header.command, header.len), (3, 0));
+}
+
+#[test]
+fn random_slice_roundtrip_test() {
+    let mut rng = rand::thread_rng();
+    let crc16 = Config::<Crc16>::default();
+    let mut buf = [0u8; crate::ENCODED_MAX_LEN];
+    let mut d = [0u8; DATA_MAX_LEN];
+    let mut data = [0u8; DATA_MAX_LEN];
+
+    for _ in 0..10_000 {
+        let d = &mut d[..rng.gen_range(0..=DATA_MAX_LEN)];
+        rng.fill(d);
+        let address = if rng.gen_bool(0.5) {
+            Some(rng.gen_range(0..0x80))
+        } else {
+            None
+        };
+        let command = rng.gen_range(0..0x80);
+        let n = encode_into(address, command, d, &mut buf).unwrap();
+        let header = decode_into(&buf[..n], &mut data).unwrap();
+        assert_eq!((header.address, header.command), (address, command));
+        assert_eq!(data[..header.len], *d);
+        let n = encode_into_with(address, command, d, &mut buf, &crc16).unwrap();
+        let header = decode_into_with(&buf[..n], &mut data, &crc16).unwrap();
+        assert_eq!(data[..header.len], *d);
+        // the stream decoder checks frames with the same decoder
+        let (last, head) = buf[..n].split_last().unwrap();
+        let mut stream = StreamDecoder::with_config(crc16);
+        assert!(head.iter().all(|&b| stream.push_ref(b).is_none()));
+        let p = stream.push_ref(*last).unwrap().unwrap();
+        assert_eq!((p.address, p.command, p.data), (address, command, &*d));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn random_slice_std_equivalence_test() {
+    let mut rng = rand::thread_rng();
+    let crc16 = Config::<Crc16>::default();
+    let mut buf = [0u8; crate::ENCODED_MAX_LEN];
+    let mut data = [0u8; DATA_MAX_LEN];
+
+    for _ in 0..10_000 {
+        let n = rng.gen_range(0..=DATA_MAX_LEN);
+        let d: Vec<u8> = (0..n).map(|_| rng.gen()).collect();
+        let address = if rng.gen_bool(0.5) {
+            Some(rng.gen_range(0..0x80))
+        } else {
+            None
+        };
+        let command = rng.gen_range(0..0x80);
+        let wp = PacketRef {
+            address,
+            command,
+            data: &d,
+        }
+        .to_packet()
+        .unwrap();
+        let encoded = wp.encode().unwrap();
+        let n = encode_into(address, command, &d, &mut buf).unwrap();
+        assert_eq!(buf[..n], encoded[..]);
+        let encoded16 = wp.encode_with(&crc16).unwrap();
+        let n = encode_into_with(address, command, &d, &mut buf, &crc16).unwrap();
+        assert_eq!(buf[..n], encoded16[..]);
+        assert_eq!(encoded16.decode_with(&crc16).unwrap().data, wp.data);
+
+        let decoded = encoded.decode().unwrap();
+        let header = decode_into(&encoded, &mut data).unwrap();
+        assert_eq!((header.address, header.command), (address, command));
+        assert_eq!(data[..header.len], *decoded.data().unwrap_or_default());
+    }
+}
@@ -0,0 +1,312 @@
+//! Packet with inline payload storage, usable without a heap
+
+use core::fmt;
+
+use crate::frame::encoded_len_with;
+use crate::{
+    decode_into_with, encode_into_with, fmt_packet, Address, Checksum, Command, Config, PacketRef,
+    WakeError,
+};
+#[cfg(feature = "std")]
+use crate::{frame::encode_to_vec_with, Encode, Packet};
+
+/// Wake packet which keeps up to `N` payload bytes inline
+///
+/// Works like [`Packet`](crate::Packet), but needs no allocator. Pick `N` below
+/// `DATA_MAX_LEN` to save RAM: longer payloads are rejected with `BufferTooSmall`.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::HeaplessPacket;
+///
+/// let p = HeaplessPacket::<8>::new(Some(0x12), 3, &[0x00, 0xeb]).unwrap();
+/// let mut buf = [0u8; 32];
+/// let n = p.encode_into(&mut buf).unwrap();
+/// let decoded = HeaplessPacket::<8>::decode(&buf[..n]).unwrap();
+/// assert_eq!(decoded, p);
+/// ```
+#[derive(Clone, Copy)]
+pub struct HeaplessPacket<const N: usize> {
+    /// Device address (optional) [0 - 127]
+    pub address: Option<u8>,
+    /// Command [0 - 127]
+    pub command: u8,
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for HeaplessPacket<N> {
+    fn default() -> Self {
+        HeaplessPacket {
+            address: None,
+            command: 0,
+            data: [0; N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> HeaplessPacket<N> {
+    /// Create a packet, `data` is copied inline
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address (optional) [0 - 127]
+    /// * `command` - command [0 - 127]
+    /// * `data` - data load, might be empty
+    ///
+    /// # Output
+    ///
+    /// * `Result<HeaplessPacket<N>, WakeError>` - packet, `WrongAddrRange` or `WrongCmdRange` if
+    ///   a field is out of range, `BufferTooSmall` if `data` is longer than `N`
+    ///
+    pub fn new(address: Option<u8>, command: u8, data: &[u8]) -> Result<Self, WakeError> {
+        if let Some(address) = address {
+            Address::new(address)?;
+        }
+        Command::new(command)?;
+        let mut p = HeaplessPacket {
+            address,
+            command,
+            ..Default::default()
+        };
+        p.set_data(data)?;
+        Ok(p)
+    }
+
+    /// Data load, empty if there is no data
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    /// Replace data load
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - new data load, might be empty
+    ///
+    /// # Output
+    ///
+    /// * `Result<(), WakeError>` - `BufferTooSmall` if `data` is longer than `N`
+    ///
+    pub fn set_data(&mut self, data: &[u8]) -> Result<(), WakeError> {
+        if data.len() > N {
+            return Err(WakeError::BufferTooSmall {
+                needed: data.len(),
+                available: N,
+            });
+        }
+        self.data[..data.len()].copy_from_slice(data);
+        self.len = data.len();
+        Ok(())
+    }
+
+    /// Exact length of the encoded packet, see [`Packet::encoded_len`](crate::Packet::encoded_len)
+    pub fn encoded_len(&self) -> Result<usize, WakeError> {
+        self.encoded_len_with(&Config::new())
+    }
+
+    /// Exact length of the encoded packet with the given link configuration
+    pub fn encoded_len_with<C: Checksum>(&self, config: &Config<C>) -> Result<usize, WakeError> {
+        encoded_len_with(self.address, self.command, self.data(), config)
+    }
+
+    /// Encode packet to wake format into a slice, see [`encode_into`](crate::encode_into)
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, WakeError> {
+        self.encode_into_with(buf, &Config::new())
+    }
+
+    /// Encode packet to wake format into a slice with the given link configuration
+    pub fn encode_into_with<C: Checksum>(
+        &self,
+        buf: &mut [u8],
+        config: &Config<C>,
+    ) -> Result<usize, WakeError> {
+        encode_into_with(self.address, self.command, self.data(), buf, config)
+    }
+
+    /// Decode packet from a wake frame, see [`decode_into`](crate::decode_into)
+    pub fn decode(frame: &[u8]) -> Result<Self, WakeError> {
+        Self::decode_with(frame, &Config::new())
+    }
+
+    /// Decode packet from a wake frame with the given link configuration
+    pub fn decode_with<C: Checksum>(frame: &[u8], config: &Config<C>) -> Result<Self, WakeError> {
+        let mut p = Self::default();
+        let header = decode_into_with(frame, &mut p.data, config)?;
+        p.address = header.address;
+        p.command = header.command;
+        p.len = header.len;
+        Ok(p)
+    }
+}
+
+impl<const N: usize> PartialEq for HeaplessPacket<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+            && self.command == other.command
+            && self.data() == other.data()
+    }
+}
+
+impl<const N: usize> Eq for HeaplessPacket<N> {}
+
+impl<const N: usize> fmt::Debug for HeaplessPacket<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeaplessPacket")
+            .field("address", &self.address)
+            .field("command", &self.command)
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+impl<const N: usize> fmt::Display for HeaplessPacket<N> {
+    /// Show packet in human readable format, same as `Packet`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = match self.len {
+            0 => None,
+            _ => Some(self.data()),
+        };
+        fmt_packet(f, self.address, self.command, data)
+    }
+}
+
+/// Encode packet to wake format
+#[cfg(feature = "std")]
+impl<const N: usize> Encode for HeaplessPacket<N> {
+    fn encode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Vec<u8>, WakeError> {
+        let mut encoded = Vec::new();
+        encode_to_vec_with(
+            self.address,
+            self.command,
+            self.data(),
+            &mut encoded,
+            config,
+        )?;
+        Ok(encoded)
+    }
+}
+
+/// Borrow a heapless packet, e.g. to show it with a [`PacketFormatter`](crate::PacketFormatter)
+impl<'a, const N: usize> From<&'a HeaplessPacket<N>> for PacketRef<'a> {
+    fn from(p: &'a HeaplessPacket<N>) -> Self {
+        PacketRef {
+            address: p.address,
+            command: p.command,
+            data: p.data(),
+        }
+    }
+}
+
+/// Fails with `WrongAddrRange` or `WrongCmdRange` if a field is out of range
+#[cfg(feature = "std")]
+impl<const N: usize> TryFrom<HeaplessPacket<N>> for Packet {
+    type Error = WakeError;
+
+    fn try_from(p: HeaplessPacket<N>) -> Result<Self, Self::Error> {
+        Packet::try_from(PacketRef {
+            address: p.address,
+            command: p.command,
+            data: p.data(),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> TryFrom<Packet> for HeaplessPacket<N> {
+    type Error = WakeError;
+
+    /// Fails with `BufferTooSmall` if the data load is longer than `N`
+    fn try_from(p: Packet) -> Result<Self, Self::Error> {
+        let p = PacketRef::from(&p);
+        HeaplessPacket::new(p.address, p.command, p.data)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+use crate::Decode;
+
+#[test]
+fn heapless_new_test() {
+    let p = HeaplessPacket::<2>::new(Some(0x7f), 0x7f, &[1, 2]).unwrap();
+    assert_eq!(
+        (p.address, p.command, p.data()),
+        (Some(0x7f), 0x7f, &[1, 2][..])
+    );
+    assert_eq!(
+        HeaplessPacket::<2>::new(Some(0x80), 3, &[]),
+        Err(WakeError::WrongAddrRange { address: 0x80 })
+    );
+    assert_eq!(
+        HeaplessPacket::<2>::new(None, 0xff, &[]),
+        Err(WakeError::WrongCmdRange { command: 0xff })
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn heapless_encode_decode_test() {
+    let p = HeaplessPacket::<4>::new(Some(0x40), 0x40, &[0xC0, 0xDB]).unwrap();
+    let encoded = p.encode().unwrap();
+    assert_eq!(p.encoded_len(), Ok(encoded.len()));
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![0xC0, 0xDB])
+        .build()
+        .unwrap();
+    assert_eq!(encoded, wp.encode().unwrap());
+    assert_eq!(HeaplessPacket::<4>::decode(&encoded), Ok(p));
+    assert_eq!(
+        HeaplessPacket::<1>::decode(&encoded),
+        Err(WakeError::BufferTooSmall {
+            needed: 2,
+            available: 1
+        })
+    );
+    assert_eq!(
+        HeaplessPacket::<1>::new(None, 1, &[1, 2]),
+        Err(WakeError::BufferTooSmall {
+            needed: 2,
+            available: 1
+        })
+    );
+    assert_eq!(p.to_string(), wp.to_string());
+    let empty = HeaplessPacket::<0>::new(None, 5, &[]).unwrap();
+    assert_eq!(empty.to_string(), "ADDR: ----\nCMD:  0x05\nDATA: none\n");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn heapless_conversion_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, 0xeb])
+        .build()
+        .unwrap();
+    let p = HeaplessPacket::<2>::try_from(wp.encode().unwrap().decode().unwrap()).unwrap();
+    assert_eq!(p.address, Some(0x12));
+    assert_eq!(p.command, 3);
+    assert_eq!(p.data(), &[0x00, 0xeb]);
+    assert_eq!(Packet::try_from(p), Ok(wp.clone()));
+    assert_eq!(
+        HeaplessPacket::<1>::try_from(wp).err(),
+        Some(WakeError::BufferTooSmall {
+            needed: 2,
+            available: 1
+        })
+    );
+
+    let wp = Packet::try_from(HeaplessPacket::<2>::default()).unwrap();
+    assert_eq!(wp.data(), None);
+    let mut wrong = HeaplessPacket::<2>::new(None, 3, &[]).unwrap();
+    wrong.address = Some(0x80);
+    assert_eq!(
+        Packet::try_from(wrong),
+        Err(WakeError::WrongAddrRange { address: 0x80 })
+    );
+}
@@ -0,0 +1,371 @@
+//! Blocking packet reader and writer over `std::io` (serial ports, sockets, pipes, etc.)
+
+use std::io::{ErrorKind, Read, Write};
+
+use crate::frame::encode_to_vec_with;
+use crate::{Checksum, Config, Crc8, Packet, PacketRef, StreamDecoder, StreamError};
+
+/// Read buffer length of [`PacketReader`]
+const READ_BUF_LEN: usize = 64;
+
+/// Reads packets from a `std::io::Read`, frames may be split between reads
+///
+/// Read timeouts (e.g. of a serial port) are returned as `StreamError::Io` of the `TimedOut`
+/// or `WouldBlock` kind. The received part of a frame is kept, so just call
+/// [`PacketReader::read_packet`] again.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::PacketReader;
+///
+/// let rx: &[u8] = &[0x12, 0xC0, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6b];
+/// let mut reader = PacketReader::new(rx);
+/// let p = reader.read_packet().unwrap();
+/// assert_eq!(p.command, 3);
+/// ```
+pub struct PacketReader<R, C = Crc8> {
+    reader: R,
+    decoder: StreamDecoder<C>,
+    buf: [u8; READ_BUF_LEN],
+    pos: usize,
+    len: usize,
+}
+
+impl<R: Read> PacketReader<R> {
+    /// Create a CRC-8 packet reader
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, Config::new())
+    }
+}
+
+impl<R: Read, C: Checksum> PacketReader<R, C> {
+    /// Create a packet reader with the given link configuration
+    pub fn with_config(reader: R, config: Config<C>) -> Self {
+        PacketReader {
+            reader,
+            decoder: StreamDecoder::with_config(config),
+            buf: [0; READ_BUF_LEN],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Read the next packet
+    ///
+    /// # Output
+    ///
+    /// * `Result<Packet, StreamError>` - packet, broken frame error or I/O error.
+    ///   An `UnexpectedEof` I/O error is returned when the reader has no more data.
+    ///
+    pub fn read_packet(&mut self) -> Result<Packet, StreamError> {
+        loop {
+            // 1: Decode buffered bytes
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+                if let Some(decoded) = self.decoder.push(byte) {
+                    return Ok(decoded?);
+                }
+            }
+            // 2: Read more bytes
+            match self.reader.read(&mut self.buf) {
+                Ok(0) => return Err(StreamError::Io(ErrorKind::UnexpectedEof.into())),
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Underlying reader, reading from it directly may break the current frame
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwrap the reader, buffered bytes and the current frame are lost
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Packets and errors until EOF, see [`PacketReader::read_packet`]
+impl<R: Read, C: Checksum> Iterator for PacketReader<R, C> {
+    type Item = Result<Packet, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_packet() {
+            Err(e) if e.io_kind() == Some(ErrorKind::UnexpectedEof) => None,
+            decoded => Some(decoded),
+        }
+    }
+}
+
+/// Writes packets to a `std::io::Write`
+///
+/// Packets are encoded into an internal buffer and written out by [`PacketWriter::flush`].
+/// The buffer is not limited, it grows with every packet written without a flush. After a
+/// write timeout the unsent bytes are kept, so `flush` can be called again.
+/// The buffer is written out and the writer is flushed on drop, errors are ignored then.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{Packet, PacketWriter};
+///
+/// let mut writer = PacketWriter::new(Vec::new());
+/// let p = Packet::builder().command(3).build().unwrap();
+/// writer.write_packet(&p).unwrap();
+/// writer.flush().unwrap();
+/// assert_eq!(writer.get_ref(), &vec![0xC0, 0x03, 0x00, 0xeb]);
+/// ```
+pub struct PacketWriter<W: Write, C = Crc8> {
+    writer: W,
+    config: Config<C>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<W: Write> PacketWriter<W> {
+    /// Create a CRC-8 packet writer
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::new())
+    }
+}
+
+impl<W: Write, C: Checksum> PacketWriter<W, C> {
+    /// Create a packet writer with the given link configuration
+    pub fn with_config(writer: W, config: Config<C>) -> Self {
+        PacketWriter {
+            writer,
+            config,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Encode a packet into the buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - `&Packet` or `PacketRef`
+    ///
+    /// # Output
+    ///
+    /// * `Result<(), StreamError>` - encoding error, nothing is buffered then
+    ///
+    pub fn write_packet<'a>(
+        &mut self,
+        packet: impl Into<PacketRef<'a>>,
+    ) -> Result<(), StreamError> {
+        let p = packet.into();
+        encode_to_vec_with(p.address, p.command, p.data, &mut self.buf, &self.config)?;
+        Ok(())
+    }
+
+    /// Write out the buffer and flush the writer
+    pub fn flush(&mut self) -> Result<(), StreamError> {
+        while self.pos < self.buf.len() {
+            match self.writer.write(&self.buf[self.pos..]) {
+                Ok(0) => return Err(StreamError::Io(ErrorKind::WriteZero.into())),
+                Ok(n) => self.pos += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.buf.clear();
+        self.pos = 0;
+        Ok(self.writer.flush()?)
+    }
+
+    /// Underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Underlying writer, writing to it directly may break the current frame
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+impl<W: Write, C> Drop for PacketWriter<W, C> {
+    fn drop(&mut self) {
+        if self.pos < self.buf.len() && self.writer.write_all(&self.buf[self.pos..]).is_err() {
+            return;
+        }
+        let _ = self.writer.flush();
+    }
+}
+
+/// Reader which times out before every chunk, like a serial port
+#[cfg(test)]
+struct SlowReader {
+    chunks: Vec<Vec<u8>>,
+    ready: bool,
+}
+
+#[cfg(test)]
+impl Read for SlowReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if !self.ready {
+            self.ready = true;
+            return Err(ErrorKind::TimedOut.into());
+        }
+        self.ready = false;
+        if self.chunks.is_empty() {
+            return Ok(0);
+        }
+        let chunk = self.chunks.remove(0);
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        Ok(chunk.len())
+    }
+}
+
+/// Writer which times out before every byte
+#[cfg(test)]
+struct SlowWriter {
+    written: Vec<u8>,
+    ready: bool,
+}
+
+#[cfg(test)]
+impl Write for SlowWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.ready = !self.ready;
+        if !self.ready {
+            return Err(ErrorKind::TimedOut.into());
+        }
+        self.written.push(buf[0]);
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer which keeps the bytes until it is flushed
+#[cfg(test)]
+struct Buffered<'a> {
+    pending: Vec<u8>,
+    out: &'a mut Vec<u8>,
+}
+
+#[cfg(test)]
+impl Write for Buffered<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.append(&mut self.pending);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use crate::{Encode, WakeError, FEND};
+
+#[test]
+fn packet_reader_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, FEND, 0xeb])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut rx = vec![0x00, FEND, 0x03, 0x01, 0xdb, 0x00]; // noise and a broken frame
+    rx.extend(&encoded);
+    rx.extend(&encoded);
+    let decoded: Vec<_> = PacketReader::new(&rx[..]).collect();
+    assert_eq!(decoded.len(), 3);
+    assert!(matches!(
+        decoded[0],
+        Err(StreamError::Wake(WakeError::DestuffingFailed {
+            offset: 4,
+            byte: 0x00
+        }))
+    ));
+    assert_eq!(decoded[1].as_ref().unwrap().data, wp.data);
+    assert_eq!(decoded[2].as_ref().unwrap().command, wp.command);
+}
+
+#[test]
+fn packet_reader_timeout_test() {
+    let wp = Packet::builder()
+        .command(1)
+        .payload(vec![1, 2, 3])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut reader = PacketReader::new(SlowReader {
+        chunks: vec![encoded[..3].to_vec(), encoded[3..].to_vec()],
+        ready: false,
+    });
+    let timeout = Some(ErrorKind::TimedOut);
+    assert_eq!(reader.read_packet().unwrap_err().io_kind(), timeout);
+    // the first chunk is kept after the second timeout
+    assert_eq!(reader.read_packet().unwrap_err().io_kind(), timeout);
+    assert_eq!(reader.read_packet().unwrap().data, wp.data);
+    assert_eq!(reader.next().unwrap().unwrap_err().io_kind(), timeout);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn packet_writer_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![FEND])
+        .build()
+        .unwrap();
+    let mut writer = PacketWriter::new(SlowWriter {
+        written: vec![],
+        ready: true,
+    });
+    writer.write_packet(&wp).unwrap();
+    writer.write_packet(PacketRef::from(&wp)).unwrap();
+    let wrong = PacketRef {
+        command: 0x80,
+        ..Default::default()
+    };
+    assert!(matches!(
+        writer.write_packet(wrong),
+        Err(StreamError::Wake(WakeError::WrongCmdRange {
+            command: 0x80
+        }))
+    ));
+    assert!(writer.get_ref().written.is_empty());
+    // retry after every timeout
+    let mut timeouts = 0;
+    while let Err(e) = writer.flush() {
+        assert_eq!(e.io_kind(), Some(ErrorKind::TimedOut));
+        timeouts += 1;
+    }
+    let mut encoded = wp.encode().unwrap();
+    encoded.extend(wp.encode().unwrap());
+    assert_eq!(timeouts, encoded.len());
+    assert_eq!(writer.get_ref().written, encoded);
+    // the writer is flushed on drop
+    let mut out = Vec::new();
+    let mut writer = PacketWriter::new(Buffered {
+        pending: vec![],
+        out: &mut out,
+    });
+    writer.write_packet(&wp).unwrap();
+    drop(writer);
+    assert_eq!(out, wp.encode().unwrap());
+}
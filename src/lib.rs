@@ -1,13 +1,109 @@
 #![crate_name = "wake_rs"]
+#![cfg_attr(not(feature = "std"), no_std)]
 //! `Wake` is a serial communication protocol highly optimized for microcontrollers.
 //! `wake-rs` is a library written in Rust for encoding/decoding Wake protocol packets.
+//!
+//! The slice-based codec ([`encode_into`], [`decode_into`], [`PacketRef`]) and the device-side
+//! [`Dispatcher`] never allocate and work without `std`. Disable default features to use them
+//! on a microcontroller:
+//!
+//! ```toml
+//! wake-rs = { version = "0.2", default-features = false }
+//! ```
+//!
+//! `Packet`, `Encode`, `Decode`, `PacketReader`, `PacketWriter` and the `Packet`-returning
+//! `StreamDecoder` methods require the `std` feature (enabled by default).
+//!
+//! Optional features:
+//!
+//! * `tokio` - `WakeCodec` for `tokio_util::codec::Framed` and `AsyncWakeClient`
+//! * `futures` - `PacketStream` and `PacketSink` over `futures::io::AsyncRead`/`AsyncWrite`
+//! * `serde` - `Serialize`/`Deserialize` for `Packet`, `Address`, `Command` and errors
+//! * `serialport` - `WakePort`, a serial port sending and receiving packets
 
 #[cfg(test)]
 extern crate rand;
 
-#[cfg(test)]
+use core::fmt;
+#[cfg(all(test, feature = "std"))]
 use rand::Rng;
-use std::fmt;
+
+#[cfg(feature = "tokio")]
+mod async_client;
+#[cfg(feature = "futures")]
+mod async_io;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+mod catalog;
+mod checksum;
+#[cfg(feature = "std")]
+mod client;
+#[cfg(feature = "tokio")]
+mod codec;
+mod config;
+mod dispatcher;
+mod encode_iter;
+mod error;
+mod formatter;
+mod frame;
+mod heapless;
+#[cfg(feature = "std")]
+mod io;
+mod packet_ref;
+#[cfg(feature = "std")]
+mod scan;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod serde_impl;
+#[cfg(feature = "serialport")]
+mod serial;
+#[cfg(feature = "std")]
+mod server;
+pub mod std_commands;
+mod stream;
+#[cfg(feature = "std")]
+mod text;
+mod types;
+
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncWakeClient;
+#[cfg(feature = "futures")]
+pub use async_io::{PacketSink, PacketStream};
+#[cfg(feature = "std")]
+pub use builder::PacketBuilder;
+#[cfg(feature = "std")]
+pub use catalog::CommandCatalog;
+pub use checksum::{Checksum, Crc16, Crc8, NoCrc};
+#[cfg(feature = "std")]
+pub use client::{ClientError, IoTransport, RetryPolicy, Transport, WakeClient};
+#[cfg(feature = "tokio")]
+pub use codec::WakeCodec;
+pub use config::Config;
+pub use dispatcher::{CommandFn, Dispatcher};
+pub use encode_iter::EncodeIter;
+#[cfg(feature = "std")]
+pub use error::StreamError;
+pub use error::{WakeError, WakeErrorKind};
+pub use formatter::{PacketDisplay, PacketFormatter};
+pub use frame::{decode_into, decode_into_with, encode_into, encode_into_with, Header};
+pub use heapless::HeaplessPacket;
+#[cfg(feature = "std")]
+pub use io::{PacketReader, PacketWriter};
+pub use packet_ref::PacketRef;
+#[cfg(feature = "std")]
+pub use scan::{scan, scan_with, ScanReport};
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use serde_impl::serde_array;
+#[cfg(feature = "serialport")]
+pub use serial::WakePort;
+#[cfg(feature = "std")]
+pub use server::{Handler, WakeServer};
+#[cfg(feature = "std")]
+pub use stream::Feed;
+pub use stream::StreamDecoder;
+#[cfg(feature = "std")]
+pub use text::{ParseErrorKind, ParsePacketError};
+pub use types::{Address, Command, ErrorCode};
 
 const FEND: u8 = 0xC0;
 const FESC: u8 = 0xDB;
@@ -16,190 +112,268 @@ const TFESC: u8 = 0xDD;
 
 const ADDR_MASK: u8 = 0x80;
 const CRC_INIT: u8 = 0xDE;
-const PACKET_MIN_LEN: usize = 4;
+const PACKET_MIN_LEN: usize = 3; // FEND, CMD, N (without CRC)
+const PACKET_MAX_LEN: usize = 4 + DATA_MAX_LEN + 2; // FEND, ADDR, CMD, N, DATA, CRC-16
 
-/// Maximum supported data length. Might be reduced depends on available resources.
+/// Maximum supported data length. Might be reduced depends on available resources, see [`Config`].
 pub const DATA_MAX_LEN: usize = 0xff;
 
-/// Wake decoder/encoder errors
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum WakeError {
-    TooShortPacket,
-    CannotFindStart,
-    DestuffingFailed,
-    WrongPacketLength,
-    WrongPacketCrc,
-    WrongAddrRange,
-    WrongCmdRange,
-}
-
-impl std::error::Error for WakeError {
-    fn description(&self) -> &str {
-        match *self {
-            WakeError::TooShortPacket => "Too short packet",
-            WakeError::CannotFindStart => "Can't find a start of the packet",
-            WakeError::DestuffingFailed => "De-stuffing failed",
-            WakeError::WrongPacketLength => "Wrong packet length",
-            WakeError::WrongPacketCrc => "Wrong packet CRC",
-            WakeError::WrongAddrRange => "Address is out of range [0 - 127]",
-            WakeError::WrongCmdRange => "Command is out of range [0 - 127]",
-        }
-    }
-}
-
-impl fmt::Display for WakeError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Wake error: {:?}", self)
-    }
-}
+/// Maximum length of an encoded frame (every byte after FEND is stuffed), fits any `encode_into` output
+pub const ENCODED_MAX_LEN: usize = 1 + 2 * (PACKET_MAX_LEN - 1);
 
 /// Wake packet: address, command, and data
-#[derive(Default)]
+///
+/// Address and command are checked when they are created, use [`Packet::builder`] to
+/// build a packet from raw values. The data load is only changed by [`Packet::set_data`],
+/// which checks its length, so every packet can be encoded with the default configuration.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Packet {
-    /// Device address (optional) [0 - 127]
-    pub address: Option<u8>,
-    /// Command [0 - 127]
-    pub command: u8,
-    /// Data load (optional)
-    pub data: Option<Vec<u8>>,
+    /// Device address (optional)
+    pub address: Option<Address>,
+    /// Command
+    pub command: Command,
+    /// Data load, empty if there is none
+    data: Vec<u8>,
 }
 
+/// Hex dump by default, `{:#}` gives the text notation parsed by `str::parse`, e.g. `@12 #03 [00 eb]`
+#[cfg(feature = "std")]
 impl fmt::Display for Packet {
-    /// Show error in human readable format
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let addr = match self.address {
-            Some(a) => format!("ADDR: 0x{:02X}", a),
-            None => "ADDR: ----".to_string(),
-        };
-
-        let cmd = format!("CMD:  0x{:02X}", self.command);
-
-        let data = match &self.data {
-            Some(d) => {
-                let mut print = format!("DATA: {} bytes\n", d.len());
-                print.push_str("     0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f");
-                for (i, item) in d.iter().enumerate() {
-                    if (i == 0) | (i % 16 == 0) {
-                        print.push_str(&format!("\n{:02x}: ", i));
-                    }
-                    print.push_str(&format!("{:02x} ", item));
-                }
-                print
-            }
-            None => ("DATA: none").to_string(),
-        };
-        write!(f, "{}\n{}\n{}\n", addr, cmd, data)
+        fmt_packet(
+            f,
+            self.address.map(u8::from),
+            self.command.get(),
+            self.data(),
+        )
     }
 }
 
-trait Wake {
-    fn crc(&self) -> u8;
-    fn stuff(&self) -> Vec<u8>;
-    fn dry(&self) -> Result<Vec<u8>, WakeError>;
-}
-
-/// Calculate CRC sum of data in a vector
-impl Wake for Vec<u8> {
-    /// # Input
+#[cfg(feature = "std")]
+impl Packet {
+    /// Builder which checks raw values
     ///
-    /// * 'Vec<u8>` - input data
+    /// # Example
     ///
-    /// # Output
+    /// ```
+    /// extern crate wake_rs;
+    /// use wake_rs::Packet;
     ///
-    /// * `u8` - Calculated CRC
+    /// let p = Packet::builder()
+    ///     .address(0x12)
+    ///     .command(3)
+    ///     .payload([0x00, 0xeb])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(p.command, 3);
+    /// assert!(Packet::builder().command(0x80).build().is_err());
+    /// ```
     ///
-    fn crc(&self) -> u8 {
-        let mut crc: u8 = CRC_INIT;
+    pub fn builder() -> PacketBuilder {
+        PacketBuilder::default()
+    }
 
-        let mut crc8 = |data| {
-            let mut b = data;
-            for _ in 0..8 {
-                crc = if (b ^ crc) & 1 == 1 {
-                    ((crc ^ 0x18) >> 1) | 0x80
-                } else {
-                    (crc >> 1) & !0x80
-                };
-                b >>= 1;
-            }
-        };
+    /// Data load, `None` if there is no data
+    pub fn data(&self) -> Option<&[u8]> {
+        if self.data.is_empty() {
+            None
+        } else {
+            Some(&self.data)
+        }
+    }
 
-        for n in self {
-            crc8(*n);
+    /// Unwrap the data load, `None` if there is no data
+    pub fn into_data(self) -> Option<Vec<u8>> {
+        if self.data.is_empty() {
+            None
+        } else {
+            Some(self.data)
         }
-        crc
     }
 
-    /// Byte stuffing in a vector
+    /// Replace the data load
     ///
     /// # Arguments
     ///
-    /// * `data: &Vec<u8>` - input data
+    /// * `data` - new data load, empty for no data
     ///
     /// # Output
     ///
-    /// * `Vec<u8>` - output data
-    ///
-    fn stuff(&self) -> Vec<u8> {
-        assert!(self.len() >= (PACKET_MIN_LEN - 1)); // without CRC
-        assert_eq!(self[0], FEND);
-        let mut stuffed: Vec<u8> = vec![self[0]];
-        for x in &self[1..] {
-            match *x {
-                FESC => {
-                    stuffed.push(FESC);
-                    stuffed.push(TFESC);
-                }
-                FEND => {
-                    stuffed.push(FESC);
-                    stuffed.push(TFEND);
-                }
-                _ => stuffed.push(*x),
-            }
+    /// * `Result<(), WakeError>` - `TooLongData` if it is longer than `DATA_MAX_LEN`, the
+    ///   packet is left unchanged then
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate wake_rs;
+    /// use wake_rs::Packet;
+    ///
+    /// let mut p = Packet::builder().command(3).build().unwrap();
+    /// p.set_data([0x00, 0xeb]).unwrap();
+    /// assert_eq!(p.data(), Some(&[0x00, 0xeb][..]));
+    /// assert!(p.set_data(vec![0; 256]).is_err());
+    /// p.set_data([]).unwrap();
+    /// assert_eq!(p.data(), None);
+    /// ```
+    ///
+    pub fn set_data(&mut self, data: impl Into<Vec<u8>>) -> Result<(), WakeError> {
+        let data = data.into();
+        if data.len() > DATA_MAX_LEN {
+            return Err(WakeError::TooLongData {
+                len: data.len(),
+                max: DATA_MAX_LEN,
+            });
         }
-        stuffed
+        self.data = data;
+        Ok(())
+    }
+
+    /// Exact length of the encoded packet, e.g. to size a DMA buffer before encoding
+    ///
+    /// # Output
+    ///
+    /// * `Result<usize, WakeError>` - number of bytes `encode` produces or the error it returns
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate wake_rs;
+    /// use wake_rs::Encode;
+    ///
+    /// let p = wake_rs::Packet::builder().command(3).payload([0xC0]).build().unwrap();
+    /// assert_eq!(p.encoded_len(), Ok(p.encode().unwrap().len()));
+    /// ```
+    ///
+    pub fn encoded_len(&self) -> Result<usize, WakeError> {
+        self.encoded_len_with(&Config::new())
+    }
+
+    /// Exact length of the encoded packet with the given link configuration
+    pub fn encoded_len_with<C: Checksum>(&self, config: &Config<C>) -> Result<usize, WakeError> {
+        PacketRef::from(self).encoded_len_with(config)
+    }
+
+    /// Encode packet to wake format into a slice, see [`encode_into`]
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, WakeError> {
+        self.encode_into_with(buf, &Config::new())
+    }
+
+    /// Encode packet to wake format into a slice with the given link configuration
+    pub fn encode_into_with<C: Checksum>(
+        &self,
+        buf: &mut [u8],
+        config: &Config<C>,
+    ) -> Result<usize, WakeError> {
+        PacketRef::from(self).encode_into_with(buf, config)
+    }
+
+    /// Iterator over the encoded bytes, see [`EncodeIter`]
+    pub fn encode_iter(&self) -> Result<EncodeIter<'_>, WakeError> {
+        PacketRef::from(self).encode_iter()
     }
 
-    /// Byte destuffing in a vector
-    /// /// Translate stuffed bytes into normal data
+    /// Iterator over the encoded bytes with the given link configuration
+    pub fn encode_iter_with<C: Checksum>(
+        &self,
+        config: &Config<C>,
+    ) -> Result<EncodeIter<'_, C>, WakeError> {
+        PacketRef::from(self).encode_iter_with(config)
+    }
+
+    /// Append encoded packet to a caller-owned vector, its capacity is reused
     ///
     /// # Arguments
     ///
-    /// * `data` - Input data
+    /// * `out` - output vector, left unchanged on error
     ///
     /// # Output
     ///
-    /// * `Result<Vec<u8>, WakeError>` - Destuffed data wrapped in Result
-    ///
-    fn dry(&self) -> Result<Vec<u8>, WakeError> {
-        let mut output: Vec<u8> = vec![];
-        let mut i = 0;
-        while i < self.len() {
-            match self[i] {
-                FESC => {
-                    if i > (self.len() - 2) {
-                        return Err(WakeError::WrongPacketLength);
-                    }
-                    output.push(match self[i + 1] {
-                        TFESC => FESC,
-                        TFEND => FEND,
-                        _ => return Err(WakeError::DestuffingFailed),
-                    });
-                    i += 1;
-                }
-                _ => output.push(self[i]),
+    /// * `Result<usize, WakeError>` - number of bytes appended or error
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate wake_rs;
+    ///
+    /// let mut tx = Vec::with_capacity(64);
+    /// for command in 1..4 {
+    ///     let p = wake_rs::Packet::builder().command(command).build().unwrap();
+    ///     tx.clear();
+    ///     p.encode_to_vec(&mut tx).unwrap();
+    ///     // send tx
+    /// }
+    /// ```
+    ///
+    pub fn encode_to_vec(&self, out: &mut Vec<u8>) -> Result<usize, WakeError> {
+        self.encode_to_vec_with(out, &Config::new())
+    }
+
+    /// Append encoded packet to a caller-owned vector with the given link configuration
+    pub fn encode_to_vec_with<C: Checksum>(
+        &self,
+        out: &mut Vec<u8>,
+        config: &Config<C>,
+    ) -> Result<usize, WakeError> {
+        let p = PacketRef::from(self);
+        frame::encode_to_vec_with(p.address, p.command, p.data, out, config)
+    }
+}
+
+/// Default [`PacketFormatter`] dump of packet fields or the text notation for `{:#}`,
+/// shared by all packet types
+pub(crate) fn fmt_packet(
+    f: &mut fmt::Formatter,
+    address: Option<u8>,
+    command: u8,
+    data: Option<&[u8]>,
+) -> fmt::Result {
+    if f.alternate() {
+        return fmt_text(f, address, command, data);
+    }
+    PacketFormatter::new().write(f, None, address, command, data)
+}
+
+/// One-line text notation of packet fields: `@12 #03 [00 eb]`, address and data are optional
+fn fmt_text(
+    f: &mut fmt::Formatter,
+    address: Option<u8>,
+    command: u8,
+    data: Option<&[u8]>,
+) -> fmt::Result {
+    if let Some(a) = address {
+        write!(f, "@{:02x} ", a)?;
+    }
+    write!(f, "#{:02x}", command)?;
+    if let Some(d) = data {
+        f.write_str(" [")?;
+        for (i, byte) in d.iter().enumerate() {
+            match i {
+                0 => write!(f, "{:02x}", byte)?,
+                _ => write!(f, " {:02x}", byte)?,
             }
-            i += 1;
         }
-        Ok(output)
+        f.write_str("]")?;
     }
+    Ok(())
 }
 
 /// Decode data from wake format to wake packet structure
+///
+/// Decoding never panics: any input yields either a packet or a `WakeError`.
+#[cfg(feature = "std")]
 pub trait Decode {
-    fn decode(&self) -> Result<Packet, WakeError>;
+    /// Decode with the default link configuration (CRC-8, `DATA_MAX_LEN` bytes of data)
+    fn decode(&self) -> Result<Packet, WakeError> {
+        self.decode_with(&Config::new())
+    }
+
+    /// Decode with the given link configuration
+    fn decode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Packet, WakeError>;
 }
 
 /// Decode Vec<u8> from wake format to wake packet structure
+#[cfg(feature = "std")]
 impl Decode for Vec<u8> {
     /// # Output
     ///
@@ -221,61 +395,32 @@ impl Decode for Vec<u8> {
     /// }
     /// ```
     ///
-    fn decode(&self) -> Result<Packet, WakeError> {
-        // 1: Check packet length
-        if self.len() < PACKET_MIN_LEN {
-            return Err(WakeError::TooShortPacket);
-        }
-        // 2: Check START symbol (FEND)
-        if self[0] != FEND {
-            return Err(WakeError::CannotFindStart);
-        }
-        // 3: Dry packet (remove stuffed bytes)
-        let mut destuffed_pkt = self.dry()?;
-        let mut v_iter = destuffed_pkt.iter().enumerate();
-        v_iter.next(); // skip start symbol
-                       // 4: Get an address (if exists) and a command
-        let mut decoded = Packet::default();
-        let (_, d) = v_iter.next().ok_or(WakeError::TooShortPacket)?;
-        match d {
-            addr @ ADDR_MASK..=0xff => {
-                decoded.address = Some(addr & !ADDR_MASK);
-                let (_, cmd) = v_iter.next().ok_or(WakeError::TooShortPacket)?;
-                decoded.command = *cmd;
-            }
-            cmd => {
-                decoded.address = None;
-                decoded.command = *cmd;
-            }
-        };
-        // 5: Get data length
-        let (i, data_len) = v_iter.next().ok_or(WakeError::TooShortPacket)?;
-        // 8: Check data length
-        if (destuffed_pkt.len() - i - 2) != *data_len as usize {
-            return Err(WakeError::WrongPacketLength);
-        }
-        // 9: Get data
-        decoded.data = match data_len {
-            0 => None,
-            _ => Some(destuffed_pkt[i + 1..destuffed_pkt.len() - 1].to_vec()),
-        };
-        // 6: Get CRC and remove it
-        let received_crc = destuffed_pkt.remove(destuffed_pkt.len() - 1);
-        // 10: Check CRC
-        if received_crc != destuffed_pkt.to_vec().crc() {
-            Err(WakeError::WrongPacketCrc)
-        } else {
-            Ok(decoded)
-        }
+    fn decode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Packet, WakeError> {
+        // destuff into a stack buffer, the data load is the only allocation
+        let mut data = [0u8; DATA_MAX_LEN];
+        let header = decode_into_with(self, &mut data, config)?;
+        Packet::try_from(PacketRef {
+            address: header.address,
+            command: header.command,
+            data: &data[..header.len],
+        })
     }
 }
 
 /// Encode packet to wake format
+#[cfg(feature = "std")]
 pub trait Encode {
-    fn encode(&self) -> Result<Vec<u8>, WakeError>;
+    /// Encode with the default link configuration (CRC-8, `DATA_MAX_LEN` bytes of data)
+    fn encode(&self) -> Result<Vec<u8>, WakeError> {
+        self.encode_with(&Config::new())
+    }
+
+    /// Encode with the given link configuration
+    fn encode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Vec<u8>, WakeError>;
 }
 
 /// Encode packet to wake format
+#[cfg(feature = "std")]
 impl Encode for Packet {
     /// # Input
     ///
@@ -291,140 +436,171 @@ impl Encode for Packet {
     /// extern crate wake_rs;
     /// use wake_rs::Encode;
     ///
-    /// let p = wake_rs::Packet{address: Some(0x12), command: 3, data: Some(vec!{0x00, 0xeb})};
+    /// let p = wake_rs::Packet::builder().command(3).payload([0x00, 0xeb]).build().unwrap();
     /// let encoded_packet: Vec<u8> = p.encode().unwrap();
     /// ```
     ///
-    fn encode(&self) -> Result<Vec<u8>, WakeError> {
-        let mut encoded_packet: Vec<u8> = vec![];
-        // 1. FEND
-        encoded_packet.push(FEND);
-        // 2. Address, if exists
-        if let Some(addr) = self.address {
-            if addr > 0x7f {
-                return Err(WakeError::WrongAddrRange);
-            }
-            encoded_packet.push(addr | ADDR_MASK);
-        }
-        // 3. Command
-        if self.command > 0x7f {
-            return Err(WakeError::WrongCmdRange);
-        }
-        encoded_packet.push(self.command);
-        // 4. Data length; data, if exists
-        match &self.data {
-            Some(d) => {
-                encoded_packet.push(d.len() as u8);
-                encoded_packet.extend(d.iter().cloned());
-            }
-            None => encoded_packet.push(0),
-        }
-        // 5. CRC
-        encoded_packet.push(encoded_packet.crc());
-        // 6. Stuffing
-        Ok(encoded_packet.stuff())
+    fn encode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Vec<u8>, WakeError> {
+        let mut encoded = Vec::new();
+        self.encode_to_vec_with(&mut encoded, config)?;
+        Ok(encoded)
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
-fn crc_test() {
-    let xs = vec![1, 2, 3, 4, 5];
-    assert_eq!(xs.crc(), 0xd6);
-
-    let xs = vec![0xc0, 0x03, 0x00];
-    assert_eq!(xs.crc(), 0xeb);
-
-    let xs = vec![0xc0, 0x89, 0x03, 0x05, 1, 2, 3, 4, 5];
-    assert_eq!(xs.crc(), 0x69);
-}
-
-#[test]
-fn stuff_test() {
-    // Regular packet
-    let a = vec![FEND, FESC, 1, 2, 3, 4, 5, FEND]; // initial_data
-    let b = vec![FEND, FESC, TFESC, 1, 2, 3, 4, 5, FESC, TFEND]; // stuffed_data
-    assert_eq!(a.stuff(), b);
-
-    // packet with min len
-    let a = vec![FEND, 3, 0];
-    assert_eq!(a.stuff(), a);
-
-    // empty packet, should panic
-    let a = vec![];
-    let result = std::panic::catch_unwind(|| a.stuff());
-    assert!(result.is_err());
-
-    // short packet, should panic
-    let a = vec![FEND, 3];
-    let result = std::panic::catch_unwind(|| a.stuff());
-    assert!(result.is_err());
+fn display_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload((0..18).collect::<Vec<u8>>())
+        .build()
+        .unwrap();
+    assert_eq!(
+        wp.to_string(),
+        "ADDR: 0x12\nCMD:  0x03\nDATA: 18 bytes\n     \
+         0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f\n\
+         00: 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f \n\
+         10: 10 11 \n"
+    );
+    let wp = Packet::builder().command(0x7f).build().unwrap();
+    assert_eq!(wp.to_string(), "ADDR: ----\nCMD:  0x7F\nDATA: none\n");
 }
 
-#[test]
-fn dry_test() {
-    let t0 = vec![]; // empty
-    let t1 = vec![0x34]; // 1 byte
-    let t2 = vec![1, 2, 3, 4, 5, FEND]; // stuffed data without first FEND
-    let t3 = vec![FEND, FESC, TFESC, 1, 2, 3, 4, 5, FESC]; // stuffed data without last byte
-    let t4 = vec![FEND, FESC, 1, 2, 3, 4, 5, FESC, TFEND]; // stuffed data with missed 3rd byte
-    let t5 = vec![FEND, FESC, TFESC, 1, 2, 3, 4, 5, FESC, TFEND]; // well stuffed data
-    let a5 = vec![FEND, FESC, 1, 2, 3, 4, 5, FEND]; // destuffed t5
-    assert_eq!(t0.dry(), Ok(vec![]));
-    assert_eq!(t1.clone().dry(), Ok(t1));
-    assert_eq!(t2.clone().dry(), Ok(t2));
-    assert_eq!(t3.dry(), Err(WakeError::WrongPacketLength));
-    assert_eq!(t4.dry(), Err(WakeError::DestuffingFailed));
-    assert_eq!(t5.dry(), Ok(a5));
-}
+#[cfg(feature = "std")]
 #[test]
 fn encode_packet_test() {
-    // address is out of range
-    let wp = Packet {
-        address: Some(128),
-        command: 9,
-        data: Some(vec![0x12, 0x34]),
-    };
-    assert_eq!(wp.encode(), Err(WakeError::WrongAddrRange));
-    // command is out of range
+    // data is too long, address and command are checked by the builder
     let wp = Packet {
-        address: None,
-        command: 128,
-        data: Some(vec![0x12, 0x34]),
+        command: Command::new(9).unwrap(),
+        data: vec![0; DATA_MAX_LEN + 1],
+        ..Default::default()
     };
-    assert_eq!(wp.encode(), Err(WakeError::WrongCmdRange));
+    assert_eq!(
+        wp.encode(),
+        Err(WakeError::TooLongData { len: 256, max: 255 })
+    );
+    let small = Config::new().with_max_data_len(1);
+    assert_eq!(
+        wp.encode_with(&small),
+        Err(WakeError::TooLongData { len: 256, max: 1 })
+    );
+    let wp = Packet::builder()
+        .command(9)
+        .payload(vec![0x12, 0x34])
+        .build()
+        .unwrap();
+    let too_long = WakeError::TooLongData { len: 2, max: 1 };
+    assert_eq!(wp.encode_with(&small), Err(too_long));
+    assert_eq!(
+        wp.encode().unwrap().decode_with(&small).err(),
+        Some(too_long)
+    );
     // without address
-    let wp = Packet {
-        address: None,
-        command: 9,
-        data: Some(vec![0x12, 0x34]),
-    };
+    let wp = Packet::builder()
+        .command(9)
+        .payload(vec![0x12, 0x34])
+        .build()
+        .unwrap();
     assert_eq!(wp.encode(), Ok(vec![FEND, 0x09, 0x02, 0x12, 0x34, 160]));
     // with data
-    let wp = Packet {
-        address: Some(0x12),
-        command: 3,
-        data: Some(vec![0x00, 0xeb]),
-    };
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, 0xeb])
+        .build()
+        .unwrap();
     assert_eq!(
         wp.encode(),
         Ok(vec![FEND, 0x92, 0x03, 0x02, 0x00, 0xeb, 114])
     );
     // empty packet
-    let wp = Packet {
-        address: Some(0x13),
-        command: 4,
-        data: None,
-    };
+    let wp = Packet::builder().address(0x13).command(4).build().unwrap();
     assert_eq!(wp.encode(), Ok(vec![FEND, 0x93, 0x04, 0x00, 218]));
     // empty packet with stuffing
-    let wp = Packet {
-        address: Some(0x40),
-        command: 0x40,
-        data: None,
-    };
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .build()
+        .unwrap();
     assert_eq!(wp.encode(), Ok(vec![FEND, FESC, TFEND, 0x40, 0x00, 229]));
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn encode_to_vec_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![FEND, FESC])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    assert_eq!(wp.encoded_len(), Ok(10));
+    let mut buf = [0u8; 10];
+    assert_eq!(wp.encode_into(&mut buf), Ok(10));
+    assert_eq!(buf[..], encoded[..]);
+    assert_eq!(
+        wp.encode_into(&mut buf[..9]),
+        Err(WakeError::BufferTooSmall {
+            needed: 10,
+            available: 9
+        })
+    );
+    // frames are appended, capacity is reused
+    let mut out = vec![0x00];
+    assert_eq!(wp.encode_to_vec(&mut out), Ok(10));
+    assert_eq!(out[1..], encoded[..]);
+    let capacity = out.capacity();
+    out.clear();
+    wp.encode_to_vec(&mut out).unwrap();
+    assert_eq!((out.capacity(), &out[..]), (capacity, &encoded[..]));
+    // left unchanged on error
+    let small = Config::new().with_max_data_len(1);
+    let too_long = WakeError::TooLongData { len: 2, max: 1 };
+    assert_eq!(wp.encoded_len_with(&small), Err(too_long));
+    assert_eq!(wp.encode_to_vec_with(&mut out, &small), Err(too_long));
+    assert_eq!(out, encoded);
+    // CRC-16 trailer might be stuffed too
+    let crc16 = Config::<Crc16>::default();
+    for command in 0..0x80 {
+        let wp = Packet::builder().command(command).build().unwrap();
+        let encoded = wp.encode_with(&crc16).unwrap();
+        assert_eq!(wp.encoded_len_with(&crc16), Ok(encoded.len()));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn packet_data_test() {
+    // an empty payload is no data, the packet round-trips
+    let mut wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload([1])
+        .build()
+        .unwrap();
+    wp.set_data([]).unwrap();
+    assert_eq!(wp.data(), None);
+    assert_eq!(
+        wp,
+        Packet::builder().address(0x12).command(3).build().unwrap()
+    );
+    let encoded = wp.encode().unwrap();
+    assert_eq!(encoded.decode(), Ok(wp.clone()));
+    assert_eq!(format!("{:#}", wp).parse::<Packet>(), Ok(wp.clone()));
+    // too long data is rejected, the packet is unchanged
+    wp.set_data([0xC0]).unwrap();
+    assert_eq!(
+        wp.set_data(vec![0; DATA_MAX_LEN + 1]),
+        Err(WakeError::TooLongData { len: 256, max: 255 })
+    );
+    assert_eq!(wp.clone().into_data(), Some(vec![0xC0]));
+    wp.set_data(vec![0xC0; DATA_MAX_LEN]).unwrap();
+    assert_eq!(wp.encode().unwrap().decode(), Ok(wp));
+}
+
+#[cfg(feature = "std")]
 #[test]
 fn decode_wo_address_test() {
     let command = 0x03u8;
@@ -438,41 +614,69 @@ fn decode_wo_address_test() {
     good_packet.extend_from_slice(&crc);
     let decoded = good_packet.decode().unwrap(); // TODO: need unwrap?
     assert_eq!(decoded.command, command);
-    assert_eq!(decoded.data.unwrap(), data);
+    assert_eq!(decoded.into_data().unwrap(), data);
 
     let bad_packet_too_short = vec![FEND, command, n];
     let decoded = bad_packet_too_short.decode();
-    assert_eq!(decoded.err(), Some(WakeError::TooShortPacket));
+    assert_eq!(decoded.err(), Some(WakeError::TooShortPacket { len: 3 }));
 
     let mut bad_packet_wo_start = vec![command, n];
     bad_packet_wo_start.extend_from_slice(&data);
     bad_packet_wo_start.extend_from_slice(&crc);
     let decoded = bad_packet_wo_start.decode();
-    assert_eq!(decoded.err(), Some(WakeError::CannotFindStart));
+    assert_eq!(
+        decoded.err(),
+        Some(WakeError::CannotFindStart { found: command })
+    );
 
     let bad_packet_wrong_stuffing = vec![FEND, FESC, FESC, 1, 2, 3, 4, 5, FESC, TFEND]; // stuffed packed with wrong 3rd byte
     let decoded = bad_packet_wrong_stuffing.decode();
-    assert_eq!(decoded.err(), Some(WakeError::DestuffingFailed));
+    assert_eq!(
+        decoded.err(),
+        Some(WakeError::DestuffingFailed {
+            offset: 2,
+            byte: FESC
+        })
+    );
 
     let mut bad_packet_wrong_data_len = vec![FEND, command, n - 1];
     bad_packet_wrong_data_len.extend_from_slice(&data);
     bad_packet_wrong_data_len.extend_from_slice(&wrong_crc);
     let decoded = bad_packet_wrong_data_len.decode();
-    assert_eq!(decoded.err(), Some(WakeError::WrongPacketLength));
+    assert_eq!(
+        decoded.err(),
+        Some(WakeError::WrongPacketLength {
+            declared: 4,
+            actual: 5
+        })
+    );
 
     let mut bad_packet_wrong_data_len = vec![FEND, command, n + 1];
     bad_packet_wrong_data_len.extend_from_slice(&data);
     bad_packet_wrong_data_len.extend_from_slice(&wrong_crc);
     let decoded = bad_packet_wrong_data_len.decode();
-    assert_eq!(decoded.err(), Some(WakeError::WrongPacketLength));
+    assert_eq!(
+        decoded.err(),
+        Some(WakeError::WrongPacketLength {
+            declared: 6,
+            actual: 5
+        })
+    );
 
     let mut bad_packet_wrong_crc = vec![FEND, command, n];
     bad_packet_wrong_crc.extend_from_slice(&data);
     bad_packet_wrong_crc.extend_from_slice(&wrong_crc);
     let decoded = bad_packet_wrong_crc.decode();
-    assert_eq!(decoded.err(), Some(WakeError::WrongPacketCrc));
+    assert_eq!(
+        decoded.err(),
+        Some(WakeError::WrongPacketCrc {
+            expected: 0x6B,
+            received: 0x6C
+        })
+    );
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn decode_w_address_test() {
     let address = 0x09u8;
@@ -485,22 +689,135 @@ fn decode_w_address_test() {
     good_packet.extend_from_slice(&data);
     good_packet.extend_from_slice(&crc);
     let decoded = good_packet.decode();
-    assert_eq!(decoded.is_ok(), true);
+    assert!(decoded.is_ok());
     let decoded = decoded.unwrap();
     assert_eq!(decoded.address.unwrap(), address);
     assert_eq!(decoded.command, command);
-    assert_eq!(decoded.data.unwrap(), data);
+    assert_eq!(decoded.into_data().unwrap(), data);
 
     // 0x40 test
     let good_packet = vec![FEND, FESC, TFEND, 0x40, 0x00, 229];
     let decoded = good_packet.decode();
-    assert_eq!(decoded.is_ok(), true);
+    assert!(decoded.is_ok());
     let decoded = decoded.unwrap();
     assert_eq!(decoded.address.unwrap(), 0x40);
     assert_eq!(decoded.command, 0x40);
-    assert_eq!(decoded.data, None);
+    assert_eq!(decoded.data(), None);
+
+    // command is 7-bit too
+    let wrong_command = vec![FEND, address | 0x80u8, 0x83, 0x00, 0x00];
+    assert_eq!(
+        wrong_command.decode(),
+        Err(WakeError::WrongCmdRange { command: 0x83 })
+    );
+}
+
+/// Feed input into every decoder, none of them may panic
+#[cfg(all(test, feature = "std"))]
+fn decode_everywhere(input: &[u8]) {
+    fn with<C: Checksum>(input: &[u8]) {
+        let mut data = [0u8; DATA_MAX_LEN];
+        let vec = input.to_vec();
+        let config = Config::<C>::default();
+        let decoded = vec.decode_with(&config);
+        let mut buf = input.to_vec();
+        let in_place = PacketRef::decode_with(&mut buf, &config);
+        // copying and in-place decoders accept exactly the same frames
+        assert_eq!(
+            decoded.as_ref().err(),
+            in_place.as_ref().err(),
+            "{:02x?}",
+            input
+        );
+        if let (Ok(p), Ok(r)) = (decoded, in_place) {
+            assert_eq!(PacketRef::from(&p), r);
+        }
+        let _ = decode_into_with(input, &mut data, &config);
+        let _ = HeaplessPacket::<4>::decode_with(input, &config);
+        let mut stream = StreamDecoder::with_config(config.with_max_data_len(4));
+        for _ in stream.feed(input) {}
+    }
+    with::<Crc8>(input);
+    with::<Crc16>(input);
+    with::<NoCrc>(input);
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn short_input_no_panic_test() {
+    // all inputs up to 5 bytes from an alphabet of interesting bytes
+    let alphabet = [
+        0x00, 0x01, 0x02, 0x7f, 0x80, 0x85, FEND, FESC, TFEND, TFESC, 0xff,
+    ];
+    let mut inputs: Vec<Vec<u8>> = vec![vec![]];
+    for _ in 0..5 {
+        let mut longer = vec![];
+        for input in &inputs {
+            decode_everywhere(input);
+            for b in alphabet {
+                let mut next = input.clone();
+                next.push(b);
+                longer.push(next);
+            }
+        }
+        inputs = longer;
+    }
+    for input in &inputs {
+        decode_everywhere(input);
+    }
+    // all 2-byte tails after FEND, including [0xC0, 0x85, 0x01, 0x00]-like frames
+    for a in 0..=0xff {
+        for b in 0..=0xff {
+            decode_everywhere(&[FEND, a, b]);
+            decode_everywhere(&[FEND, a, b, 0x00]);
+        }
+    }
+    assert_eq!(
+        vec![FEND, 0x85, 0x01, 0x00].decode().err(),
+        Some(WakeError::WrongPacketLength {
+            declared: 0,
+            actual: 0
+        })
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn random_input_no_panic_test() {
+    let mut rng = rand::thread_rng();
+    let alphabet = [0x00, 0x01, 0x80, FEND, FESC, TFEND, TFESC];
+
+    for _ in 0..5_000 {
+        // random bytes
+        let n = rng.gen_range(0..300);
+        let mut input: Vec<u8> = (0..n).map(|_| rng.gen()).collect();
+        decode_everywhere(&input);
+        // random bytes after a start symbol, mostly special ones
+        input[..].iter_mut().for_each(|b| {
+            if rng.gen_bool(0.5) {
+                *b = alphabet[rng.gen_range(0..alphabet.len())];
+            }
+        });
+        input.insert(0, FEND);
+        decode_everywhere(&input);
+        // valid packet with corrupted bytes
+        let n = rng.gen_range(0..16);
+        let wp = Packet::builder()
+            .address(rng.gen_range(0..0x80))
+            .command(rng.gen_range(0..0x80))
+            .payload((0..n).map(|_| rng.gen()).collect::<Vec<u8>>())
+            .build()
+            .unwrap();
+        let mut encoded = wp.encode().unwrap();
+        let i = rng.gen_range(0..encoded.len());
+        encoded[i] = rng.gen();
+        let cut = rng.gen_range(0..=encoded.len());
+        decode_everywhere(&encoded);
+        decode_everywhere(&encoded[..cut]);
+    }
+}
+
+#[cfg(feature = "std")]
 #[test]
 fn random_encode_decode_test() {
     let mut rng = rand::thread_rng();
@@ -513,20 +830,14 @@ fn random_encode_decode_test() {
             d.push(rng.gen_range(0..0xff));
         }
 
-        let wp = Packet {
-            address: if address_exists {
-                Some(rng.gen_range(0..0x7f))
-            } else {
-                None
-            },
-            command: rng.gen_range(0..0x7f),
-            data: if d.len() == 0 { None } else { Some(d.clone()) },
-        };
+        let mut builder = Packet::builder().command(rng.gen_range(0..0x7f)).payload(d);
+        if address_exists {
+            builder = builder.address(rng.gen_range(0..0x7f));
+        }
+        let wp = builder.build().unwrap();
         // print!("{}\n", &wp);
         let encoded = wp.encode().unwrap();
-        let decoded = encoded.decode().unwrap();
-        assert_eq!(decoded.address, wp.address);
-        assert_eq!(decoded.command, wp.command);
-        assert_eq!(decoded.data, wp.data);
+        assert_eq!(wp.encoded_len(), Ok(encoded.len()));
+        assert_eq!(encoded.decode(), Ok(wp));
     }
 }
@@ -0,0 +1,235 @@
+//! Borrowed packet view, decoded without copying the payload
+
+use core::fmt;
+
+use crate::frame::{decode_destuffed_with, decode_in_place_with, encoded_len_with};
+use crate::{encode_into_with, fmt_packet, Checksum, Config, EncodeIter, WakeError};
+#[cfg(feature = "std")]
+use crate::{frame::encode_to_vec_with, Encode, Packet};
+
+/// Wake packet which borrows its data load
+///
+/// [`PacketRef::decode`] destuffs a frame in place and checks its CRC in a single pass, the
+/// data load then points into the same buffer. Convert it to an owned [`Packet`](crate::Packet)
+/// only when the packet has to outlive the buffer.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::PacketRef;
+///
+/// let mut buf = [0xC0, 0x92, 0x03, 0x02, 0x00, 0xeb, 114];
+/// let p = PacketRef::decode(&mut buf).unwrap();
+/// assert_eq!(p.address, Some(0x12));
+/// assert_eq!(p.command, 3);
+/// assert_eq!(p.data, &[0x00, 0xeb]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PacketRef<'a> {
+    /// Device address (optional) [0 - 127]
+    pub address: Option<u8>,
+    /// Command [0 - 127]
+    pub command: u8,
+    /// Data load, empty if there is no data
+    pub data: &'a [u8],
+}
+
+impl<'a> PacketRef<'a> {
+    /// Decode a wake frame in place
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - one encoded frame, it is destuffed in place. Its content is undefined after an error.
+    ///
+    /// # Output
+    ///
+    /// * `Result<PacketRef, WakeError>` - packet borrowing its data from `buf` or error
+    ///
+    pub fn decode(buf: &'a mut [u8]) -> Result<Self, WakeError> {
+        Self::decode_with(buf, &Config::new())
+    }
+
+    /// Decode a wake frame in place with the given link configuration
+    pub fn decode_with<C: Checksum>(
+        buf: &'a mut [u8],
+        config: &Config<C>,
+    ) -> Result<Self, WakeError> {
+        let (header, data) = decode_in_place_with(buf, config)?;
+        Ok(PacketRef {
+            address: header.address,
+            command: header.command,
+            data,
+        })
+    }
+
+    /// Exact length of the encoded packet, see [`Packet::encoded_len`](crate::Packet::encoded_len)
+    pub fn encoded_len(&self) -> Result<usize, WakeError> {
+        self.encoded_len_with(&Config::new())
+    }
+
+    /// Exact length of the encoded packet with the given link configuration
+    pub fn encoded_len_with<C: Checksum>(&self, config: &Config<C>) -> Result<usize, WakeError> {
+        encoded_len_with(self.address, self.command, self.data, config)
+    }
+
+    /// Encode packet to wake format into a slice, see [`encode_into`](crate::encode_into)
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, WakeError> {
+        self.encode_into_with(buf, &Config::new())
+    }
+
+    /// Encode packet to wake format into a slice with the given link configuration
+    pub fn encode_into_with<C: Checksum>(
+        &self,
+        buf: &mut [u8],
+        config: &Config<C>,
+    ) -> Result<usize, WakeError> {
+        encode_into_with(self.address, self.command, self.data, buf, config)
+    }
+
+    /// Iterator over the encoded bytes, see [`EncodeIter`]
+    pub fn encode_iter(&self) -> Result<EncodeIter<'a>, WakeError> {
+        EncodeIter::new(*self, &Config::new())
+    }
+
+    /// Iterator over the encoded bytes with the given link configuration
+    pub fn encode_iter_with<C: Checksum>(
+        &self,
+        config: &Config<C>,
+    ) -> Result<EncodeIter<'a, C>, WakeError> {
+        EncodeIter::new(*self, config)
+    }
+
+    /// Copy packet into an owned `Packet`, see [`Packet::builder`](crate::Packet::builder) for errors
+    #[cfg(feature = "std")]
+    pub fn to_packet(&self) -> Result<Packet, WakeError> {
+        Packet::try_from(*self)
+    }
+}
+
+impl<'a> fmt::Display for PacketRef<'a> {
+    /// Show packet in human readable format, same as `Packet`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let data = match self.data.len() {
+            0 => None,
+            _ => Some(self.data),
+        };
+        fmt_packet(f, self.address, self.command, data)
+    }
+}
+
+/// Split a destuffed frame into a wake packet
+///
+/// # Arguments
+///
+/// * `frame` - destuffed frame: FEND, address (optional), command, data length, data, CRC (`C::LEN` bytes)
+/// * `config` - link configuration
+///
+/// # Output
+///
+/// * `Result<PacketRef, WakeError>` - packet borrowing its data from `frame` or error
+///
+pub(crate) fn unpack<'a, C: Checksum>(
+    frame: &'a [u8],
+    config: &Config<C>,
+) -> Result<PacketRef<'a>, WakeError> {
+    let (header, data) = decode_destuffed_with(frame, config)?;
+    Ok(PacketRef {
+        address: header.address,
+        command: header.command,
+        data,
+    })
+}
+
+/// Encode packet to wake format
+#[cfg(feature = "std")]
+impl<'a> Encode for PacketRef<'a> {
+    fn encode_with<C: Checksum>(&self, config: &Config<C>) -> Result<Vec<u8>, WakeError> {
+        let mut encoded = Vec::new();
+        encode_to_vec_with(self.address, self.command, self.data, &mut encoded, config)?;
+        Ok(encoded)
+    }
+}
+
+/// Borrow an owned packet, e.g. to encode it without copying
+#[cfg(feature = "std")]
+impl<'a> From<&'a Packet> for PacketRef<'a> {
+    fn from(p: &'a Packet) -> Self {
+        PacketRef {
+            address: p.address.map(u8::from),
+            command: p.command.get(),
+            data: &p.data,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+use crate::Crc16;
+#[cfg(test)]
+use crate::{FEND, FESC, TFEND, TFESC};
+
+#[test]
+fn packet_ref_decode_test() {
+    let mut buf = [
+        FEND, FESC, TFEND, 0x40, 0x02, FESC, TFESC, FESC, TFEND, 0x74,
+    ];
+    let p = PacketRef::decode(&mut buf).unwrap();
+    assert_eq!(p.address, Some(0x40));
+    assert_eq!(p.command, 0x40);
+    assert_eq!(p.data, &[FESC, FEND]);
+
+    // stuffed data without last byte
+    let mut t3 = [FEND, FESC, TFESC, 1, 2, 3, 4, 5, FESC];
+    assert_eq!(
+        PacketRef::decode(&mut t3),
+        Err(WakeError::WrongPacketLength {
+            declared: 2,
+            actual: 3
+        })
+    );
+    // stuffed data with missed 3rd byte
+    let mut t4 = [FEND, FESC, 1, 2, 3, 4, 5, FESC, TFEND];
+    assert_eq!(
+        PacketRef::decode(&mut t4),
+        Err(WakeError::DestuffingFailed { offset: 2, byte: 1 })
+    );
+    // no start symbol
+    let mut t2 = [1, 2, 3, 4, 5, FEND];
+    assert_eq!(
+        PacketRef::decode(&mut t2),
+        Err(WakeError::CannotFindStart { found: 1 })
+    );
+    assert_eq!(
+        PacketRef::decode(&mut []),
+        Err(WakeError::TooShortPacket { len: 0 })
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn packet_ref_conversion_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, FEND, 0xeb])
+        .build()
+        .unwrap();
+    let crc16 = Config::<Crc16>::default();
+    let mut encoded = wp.encode_with(&crc16).unwrap();
+    assert_eq!(
+        PacketRef::from(&wp).encode_with(&crc16),
+        Ok(encoded.clone())
+    );
+    let p = PacketRef::decode_with(&mut encoded, &crc16).unwrap();
+    assert_eq!(p, PacketRef::from(&wp));
+    assert_eq!(p.to_string(), wp.to_string());
+    assert_eq!(p.to_packet(), Ok(wp));
+
+    let empty = PacketRef {
+        address: None,
+        command: 5,
+        data: &[],
+    };
+    assert_eq!(Packet::try_from(empty).unwrap().data(), None);
+    assert_eq!(empty.to_string(), "ADDR: ----\nCMD:  0x05\nDATA: none\n");
+}
@@ -0,0 +1,198 @@
+//! Extract all frames from a capture with line noise, partial and back-to-back frames
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::{Checksum, Config, Packet, StreamDecoder, WakeError, WakeErrorKind, FEND};
+
+/// Result of [`scan`]: packets, garbage and broken frames with their byte ranges
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanReport {
+    /// Valid packets
+    pub packets: Vec<(Range<usize>, Packet)>,
+    /// Bytes outside of any frame
+    pub garbage: Vec<Range<usize>>,
+    /// Broken frames
+    pub failed: Vec<(Range<usize>, WakeError)>,
+}
+
+impl ScanReport {
+    /// Number of broken frames for every error kind
+    pub fn error_counts(&self) -> BTreeMap<WakeErrorKind, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, e) in &self.failed {
+            *counts.entry(e.kind()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Add a garbage range, adjacent ranges are merged
+    fn add_garbage(&mut self, range: Range<usize>) {
+        match self.garbage.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => self.garbage.push(range),
+        }
+    }
+}
+
+/// Scan a buffer for wake frames
+///
+/// Every FEND starts a new frame. Bytes which do not belong to a frame (noise before
+/// the first FEND, after a complete frame, lone FENDs) are reported as garbage.
+/// Frames interrupted by FEND or by the end of the buffer are reported as broken.
+///
+/// # Arguments
+///
+/// * `buf` - captured bytes
+///
+/// # Output
+///
+/// * `ScanReport` - packets, garbage ranges and broken frames, in order of their offset
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+///
+/// let capture = [0x12, 0xC0, 0x03, 0x00, 0xeb, 0x34, 0xC0, 0x03, 0x00, 0xec];
+/// let report = wake_rs::scan(&capture);
+/// assert_eq!(report.packets[0].0, 1..5);
+/// assert_eq!(report.packets[0].1.command, 3);
+/// assert_eq!(report.garbage, vec![0..1, 5..6]);
+/// assert_eq!(report.failed[0].0, 6..10);
+/// assert_eq!(report.failed[0].1.kind(), wake_rs::WakeErrorKind::WrongPacketCrc);
+/// ```
+///
+pub fn scan(buf: &[u8]) -> ScanReport {
+    scan_with(buf, &Config::new())
+}
+
+/// Scan a buffer for wake frames with the given link configuration, see [`scan`]
+pub fn scan_with<C: Checksum>(buf: &[u8], config: &Config<C>) -> ScanReport {
+    let mut report = ScanReport::default();
+    let mut decoder = StreamDecoder::with_config(*config);
+    let mut start = None; // offset of the current frame
+    for (i, byte) in buf.iter().enumerate() {
+        let decoded = decoder.push(*byte);
+        if *byte == FEND {
+            // 1: New frame, the previous one is interrupted
+            if let Some(s) = start.replace(i) {
+                match decoded {
+                    Some(Err(e)) => report.failed.push((s..i, e)),
+                    _ => report.add_garbage(s..i),
+                }
+            }
+            continue;
+        }
+        match (start, decoded) {
+            // 2: Frame is complete
+            (Some(s), Some(d)) => {
+                start = None;
+                match d {
+                    Ok(p) => report.packets.push((s..i + 1, p)),
+                    Err(e) => report.failed.push((s..i + 1, e)),
+                }
+            }
+            // 3: Byte outside of a frame
+            (None, _) => report.add_garbage(i..i + 1),
+            _ => (),
+        }
+    }
+    // 4: Frame is cut by the end of the buffer
+    if let Some(s) = start {
+        match decoder.push(FEND) {
+            Some(Err(e)) => report.failed.push((s..buf.len(), e)),
+            _ => report.add_garbage(s..buf.len()),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+use crate::Encode;
+
+#[test]
+fn scan_test() {
+    let a = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![FEND, 1, 2])
+        .build()
+        .unwrap()
+        .encode()
+        .unwrap();
+    let b = Packet::builder()
+        .command(4)
+        .build()
+        .unwrap()
+        .encode()
+        .unwrap();
+    let mut bad_crc = b.clone();
+    *bad_crc.last_mut().unwrap() ^= 1;
+
+    let mut capture = vec![0x00, 0x11];
+    let a_at = capture.len();
+    capture.extend(&a);
+    capture.extend(&[0x22, 0x33]); // noise after a frame
+    let bad_at = capture.len();
+    capture.extend(&bad_crc);
+    let fends_at = capture.len();
+    capture.extend(&[FEND, FEND]);
+    let b_at = capture.len();
+    capture.extend(&b);
+    capture.extend(&b); // back-to-back
+    let cut_at = capture.len();
+    capture.extend(&a[..a.len() - 1]);
+
+    let report = scan(&capture);
+    let offsets: Vec<_> = report.packets.iter().map(|(r, _)| r.clone()).collect();
+    assert_eq!(
+        offsets,
+        vec![
+            a_at..a_at + a.len(),
+            b_at..b_at + b.len(),
+            b_at + b.len()..cut_at
+        ]
+    );
+    assert_eq!(report.packets[0].1.data(), Some(&[FEND, 1, 2][..]));
+    assert_eq!(report.packets[2].1.command, 4);
+    assert_eq!(
+        report.garbage,
+        vec![0..a_at, a_at + a.len()..bad_at, fends_at..b_at]
+    );
+    assert_eq!(
+        report.failed,
+        vec![
+            (
+                bad_at..fends_at,
+                WakeError::WrongPacketCrc {
+                    expected: *b.last().unwrap() as u16,
+                    received: *bad_crc.last().unwrap() as u16
+                }
+            ),
+            (
+                cut_at..capture.len(),
+                WakeError::WrongPacketLength {
+                    declared: 3,
+                    actual: 2
+                }
+            ),
+        ]
+    );
+    let counts = report.error_counts();
+    assert_eq!(counts.get(&WakeErrorKind::WrongPacketCrc), Some(&1));
+    assert_eq!(counts.get(&WakeErrorKind::WrongPacketLength), Some(&1));
+
+    // frame interrupted by another one
+    let mut capture = b[..2].to_vec();
+    capture.extend(&b);
+    let report = scan(&capture);
+    assert_eq!(
+        report.failed,
+        vec![(0..2, WakeError::TooShortPacket { len: 2 })]
+    );
+    assert_eq!(report.packets[0].0, 2..capture.len());
+    assert!(report.garbage.is_empty());
+    assert_eq!(report.clone(), report);
+    assert_eq!(scan(&[]), ScanReport::default());
+}
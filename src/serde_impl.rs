@@ -0,0 +1,302 @@
+//! serde support for `Packet`, other types derive it in place
+//!
+//! `Packet` is a struct of `address` (number or null), `command` (number) and `data`. The data
+//! load is a hex string (`"00eb"`) in human-readable formats like JSON and plain bytes in binary
+//! ones, see [`serde_array`] for the byte-array form. Both forms are accepted on input.
+
+use core::fmt;
+
+use serde::de::{self, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{Packet, PacketRef, DATA_MAX_LEN};
+
+/// Data load as a hex string in human-readable formats, as bytes in binary ones
+struct HexData<'a>(&'a [u8]);
+
+impl Serialize for HexData<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.collect_str(self)
+        } else {
+            s.serialize_bytes(self.0)
+        }
+    }
+}
+
+impl fmt::Display for HexData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// Data load as an array of bytes in every format
+struct ArrayData<'a>(&'a [u8]);
+
+impl Serialize for ArrayData<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.0)
+    }
+}
+
+/// Data load read from a hex string, bytes or an array of bytes
+struct Data(Vec<u8>);
+
+impl<'de> Deserialize<'de> for Data {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_any(DataVisitor).map(Data)
+        } else {
+            d.deserialize_byte_buf(DataVisitor).map(Data)
+        }
+    }
+}
+
+struct DataVisitor;
+
+impl<'de> Visitor<'de> for DataVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a hex string or an array of bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+        // whitespace between bytes is allowed, e.g. "00 eb"
+        let digits: Vec<u8> = v.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        let pairs = digits.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return Err(E::invalid_value(Unexpected::Str(v), &self));
+        }
+        pairs
+            .map(|pair| {
+                // `from_str_radix` alone would accept a sign, e.g. "+f"
+                Some(pair)
+                    .filter(|pair| pair.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|pair| core::str::from_utf8(pair).ok())
+                    .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+            })
+            .collect()
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(DATA_MAX_LEN));
+        while let Some(byte) = seq.next_element()? {
+            data.push(byte);
+        }
+        Ok(data)
+    }
+}
+
+/// Packet fields as they are read, checked by the builder afterwards
+#[derive(Deserialize)]
+#[serde(rename = "Packet")]
+struct RawPacket {
+    address: Option<u8>,
+    command: u8,
+    #[serde(default)]
+    data: Option<Data>,
+}
+
+fn serialize_packet<S: Serializer, D: Serialize>(
+    p: &Packet,
+    data: Option<D>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    let mut st = s.serialize_struct("Packet", 3)?;
+    st.serialize_field("address", &p.address)?;
+    st.serialize_field("command", &p.command)?;
+    st.serialize_field("data", &data)?;
+    st.end()
+}
+
+/// Data load is a hex string in human-readable formats, e.g. `{"address":18,"command":3,"data":"00eb"}`
+impl Serialize for Packet {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        serialize_packet(self, self.data().map(HexData), s)
+    }
+}
+
+/// Address, command and data length are checked like [`Packet::builder`] does
+impl<'de> Deserialize<'de> for Packet {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let raw = RawPacket::deserialize(d)?;
+        Packet::try_from(PacketRef {
+            address: raw.address,
+            command: raw.command,
+            data: raw.data.as_ref().map_or(&[], |data| &data.0),
+        })
+        .map_err(de::Error::custom)
+    }
+}
+
+/// Serialize a [`Packet`] with its data load as an array of bytes instead of a hex string
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use serde::{Deserialize, Serialize};
+/// use wake_rs::Packet;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Record {
+///     time: u64,
+///     #[serde(with = "wake_rs::serde_array")]
+///     packet: Packet,
+/// }
+///
+/// let packet = Packet::builder().command(3).payload([0x00, 0xeb]).build().unwrap();
+/// let json = serde_json::to_string(&Record { time: 1, packet }).unwrap();
+/// assert_eq!(json, r#"{"time":1,"packet":{"address":null,"command":3,"data":[0,235]}}"#);
+/// ```
+pub mod serde_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{serialize_packet, ArrayData};
+    use crate::Packet;
+
+    /// Serialize with the data load as an array of bytes
+    pub fn serialize<S: Serializer>(packet: &Packet, s: S) -> Result<S::Ok, S::Error> {
+        serialize_packet(packet, packet.data().map(ArrayData), s)
+    }
+
+    /// Deserialize a packet, same as `Packet::deserialize`
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Packet, D::Error> {
+        Packet::deserialize(d)
+    }
+}
+
+#[cfg(test)]
+use crate::{Address, Command, WakeError, WakeErrorKind};
+
+#[test]
+fn serde_json_test() {
+    let p = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload([0x00, 0xeb])
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&p).unwrap();
+    assert_eq!(json, r#"{"address":18,"command":3,"data":"00eb"}"#);
+    assert_eq!(serde_json::from_str::<Packet>(&json).unwrap(), p);
+    // any case, whitespace, array form and missing data are accepted
+    let same = r#"{"address":18,"command":3,"data":"00 EB"}"#;
+    assert_eq!(serde_json::from_str::<Packet>(same).unwrap(), p);
+    let same = r#"{"address":18,"command":3,"data":[0,235]}"#;
+    assert_eq!(serde_json::from_str::<Packet>(same).unwrap(), p);
+    let empty = serde_json::from_str::<Packet>(r#"{"address":null,"command":4}"#).unwrap();
+    assert_eq!(empty, Packet::builder().command(4).build().unwrap());
+    assert_eq!(
+        serde_json::to_string(&empty).unwrap(),
+        r#"{"address":null,"command":4,"data":null}"#
+    );
+    // invalid packets are rejected
+    for wrong in [
+        r#"{"address":128,"command":3}"#,
+        r#"{"address":null,"command":200}"#,
+        r#"{"address":null,"command":3,"data":"0eb"}"#,
+        r#"{"address":null,"command":3,"data":"xy"}"#,
+        r#"{"address":null,"command":3,"data":"+f"}"#,
+    ] {
+        assert!(serde_json::from_str::<Packet>(wrong).is_err(), "{}", wrong);
+    }
+    let too_long = format!(
+        r#"{{"command":3,"data":"{}"}}"#,
+        "00".repeat(DATA_MAX_LEN + 1)
+    );
+    assert!(serde_json::from_str::<Packet>(&too_long).is_err());
+
+    assert_eq!(serde_json::to_string(&Address::MAX).unwrap(), "127");
+    assert!(serde_json::from_str::<Command>("128").is_err());
+    let e = WakeError::WrongPacketCrc {
+        expected: 0x6b,
+        received: 0x6c,
+    };
+    let json = serde_json::to_string(&e).unwrap();
+    assert_eq!(
+        json,
+        r#"{"WrongPacketCrc":{"expected":107,"received":108}}"#
+    );
+    assert_eq!(serde_json::from_str::<WakeError>(&json).unwrap(), e);
+    assert_eq!(
+        serde_json::to_string(&WakeErrorKind::TooLongData).unwrap(),
+        r#""TooLongData""#
+    );
+}
+
+#[test]
+fn serde_binary_test() {
+    let packets = [
+        Packet::builder()
+            .address(0x7f)
+            .command(0x7f)
+            .payload(vec![0xC0; DATA_MAX_LEN])
+            .build()
+            .unwrap(),
+        Packet::builder().command(1).build().unwrap(),
+    ];
+    for p in packets {
+        let encoded = bincode::serialize(&p).unwrap();
+        assert_eq!(bincode::deserialize::<Packet>(&encoded).unwrap(), p);
+    }
+    let errors = [
+        WakeError::DestuffingFailed {
+            offset: 3,
+            byte: 0x12,
+        },
+        WakeError::BufferTooSmall {
+            needed: 9,
+            available: 8,
+        },
+    ];
+    for e in errors {
+        let encoded = bincode::serialize(&e).unwrap();
+        assert_eq!(bincode::deserialize::<WakeError>(&encoded).unwrap(), e);
+    }
+    let wrong = bincode::serialize(&(Some(0x80u8), 3u8, None::<Vec<u8>>)).unwrap();
+    assert!(bincode::deserialize::<Packet>(&wrong).is_err());
+}
+
+#[test]
+fn serde_array_test() {
+    #[derive(Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "serde_array")]
+        packet: Packet,
+    }
+    let packet = Packet::builder()
+        .address(1)
+        .command(2)
+        .payload([3])
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&Record {
+        packet: packet.clone(),
+    })
+    .unwrap();
+    assert_eq!(json, r#"{"packet":{"address":1,"command":2,"data":[3]}}"#);
+    assert_eq!(
+        serde_json::from_str::<Record>(&json).unwrap().packet,
+        packet
+    );
+    let encoded = bincode::serialize(&Record {
+        packet: packet.clone(),
+    })
+    .unwrap();
+    assert_eq!(
+        bincode::deserialize::<Record>(&encoded).unwrap().packet,
+        packet
+    );
+}
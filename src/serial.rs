@@ -0,0 +1,292 @@
+//! Serial port wrapper, needs the `serialport` feature
+
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::frame::encode_to_vec_with;
+use crate::{
+    Checksum, ClientError, Config, Crc8, IoTransport, Packet, PacketRef, RetryPolicy,
+    StreamDecoder, Transport, WakeClient,
+};
+
+/// Default reply timeout of [`WakePort`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Read timeout of the port, the reply timeout is checked this often
+const READ_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Receive buffer length of [`WakePort`]
+const RECV_BUF_LEN: usize = 64;
+
+/// Serial port sending and receiving packets
+///
+/// Frames might be split between reads, they are decoded as bytes arrive.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate wake_rs;
+/// use wake_rs::{Packet, WakePort};
+///
+/// let mut port = WakePort::open("/dev/ttyUSB0", 115200).unwrap();
+/// let info = Packet::builder().command(0x02).build().unwrap();
+/// let reply = port.transact(&info).unwrap();
+/// println!("{}", reply);
+/// ```
+pub struct WakePort<C = Crc8> {
+    client: WakeClient<IoTransport<Box<dyn SerialPort>>, C>,
+    config: Config<C>,
+    decoder: StreamDecoder<C>,
+    timeout: Duration,
+    buf: [u8; RECV_BUF_LEN],
+    pos: usize,
+    len: usize,
+}
+
+impl WakePort {
+    /// Open a CRC-8 port: 8N1, no flow control, input and output buffers are cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - port name, e.g. `/dev/ttyUSB0` or `COM4`
+    /// * `baud_rate` - baud rate, e.g. 115200
+    ///
+    /// # Output
+    ///
+    /// * `Result<WakePort, serialport::Error>` - port or the error of opening it
+    ///
+    pub fn open(path: &str, baud_rate: u32) -> serialport::Result<Self> {
+        Self::open_with_config(path, baud_rate, Config::new())
+    }
+}
+
+impl<C: Checksum> WakePort<C> {
+    /// Open a port with the given link configuration, see [`WakePort::open`]
+    pub fn open_with_config(
+        path: &str,
+        baud_rate: u32,
+        config: Config<C>,
+    ) -> serialport::Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .data_bits(DataBits::Eight)
+            .parity(Parity::None)
+            .stop_bits(StopBits::One)
+            .flow_control(FlowControl::None)
+            .timeout(READ_TIMEOUT)
+            .open()?;
+        Self::from_port(port, config)
+    }
+
+    /// Wrap an open port, its settings are kept except the read timeout
+    pub fn from_port(mut port: Box<dyn SerialPort>, config: Config<C>) -> serialport::Result<Self> {
+        port.set_timeout(READ_TIMEOUT)?;
+        port.clear(ClearBuffer::All)?;
+        Ok(WakePort {
+            client: WakeClient::with_config(IoTransport::new(port), config)
+                .with_timeout(DEFAULT_TIMEOUT),
+            config,
+            decoder: StreamDecoder::with_config(config),
+            timeout: DEFAULT_TIMEOUT,
+            buf: [0; RECV_BUF_LEN],
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Set the reply timeout, 100 ms by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout(timeout);
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the retry policy of [`WakePort::transact`], no retries by default
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.client = self.client.with_retry(retry);
+        self
+    }
+
+    /// Send a packet
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - `&Packet` or `PacketRef`
+    ///
+    pub fn send<'a>(&mut self, packet: impl Into<PacketRef<'a>>) -> Result<(), ClientError> {
+        let p = packet.into();
+        let mut tx = Vec::new();
+        encode_to_vec_with(p.address, p.command, p.data, &mut tx, &self.config)?;
+        Ok(self.transport().send(&tx)?)
+    }
+
+    /// Receive the next packet
+    ///
+    /// # Output
+    ///
+    /// * `Result<Packet, ClientError>` - packet, `Timeout` if no packet is received in time,
+    ///   `Protocol` for a broken frame (call `recv` again for the next one)
+    ///
+    pub fn recv(&mut self) -> Result<Packet, ClientError> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            // 1: Decode buffered bytes
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+                if let Some(decoded) = self.decoder.push(byte) {
+                    return Ok(decoded?);
+                }
+            }
+            // 2: Read more bytes
+            let n = match self.client.get_mut().recv(&mut self.buf, deadline) {
+                Ok(0) => return Err(ClientError::Io(ErrorKind::UnexpectedEof)),
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    return Err(ClientError::Timeout)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            self.pos = 0;
+            self.len = n;
+        }
+    }
+
+    /// Send a request and wait for the reply, see [`WakeClient::request`]
+    ///
+    /// Received bytes which are not read by [`WakePort::recv`] yet are dropped.
+    pub fn transact<'a>(
+        &mut self,
+        request: impl Into<PacketRef<'a>>,
+    ) -> Result<Packet, ClientError> {
+        self.discard();
+        let p = request.into();
+        self.client.request(p.address, p.command, p.data)
+    }
+
+    /// Check the link with `ECHO`, see [`WakeClient::ping`]
+    pub fn ping(&mut self, address: Option<u8>, payload: &[u8]) -> Result<(), ClientError> {
+        self.discard();
+        self.client.ping(address, payload)
+    }
+
+    /// Device description replied to `INFO`, see [`WakeClient::info`]
+    pub fn info(&mut self, address: Option<u8>) -> Result<String, ClientError> {
+        self.discard();
+        self.client.info(address)
+    }
+
+    /// Drop received bytes and clear the port buffers
+    pub fn clear(&mut self) -> Result<(), ClientError> {
+        self.discard();
+        self.port_mut()
+            .clear(ClearBuffer::All)
+            .map_err(|e| io::Error::from(e).into())
+    }
+
+    /// Underlying port
+    pub fn port(&self) -> &dyn SerialPort {
+        self.client.get_ref().get_ref().as_ref()
+    }
+
+    /// Underlying port, reading from it directly may break the current frame
+    pub fn port_mut(&mut self) -> &mut dyn SerialPort {
+        self.client.get_mut().get_mut().as_mut()
+    }
+
+    /// Drop received bytes which are not read by [`WakePort::recv`] yet
+    fn discard(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+        self.decoder.reset();
+    }
+
+    fn transport(&mut self) -> &mut IoTransport<Box<dyn SerialPort>> {
+        self.client.get_mut()
+    }
+}
+
+impl<C: Checksum> std::fmt::Debug for WakePort<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WakePort")
+            .field("port", &self.client.get_ref().get_ref().name())
+            .field("config", &self.config)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(all(test, unix))]
+use crate::Encode;
+#[cfg(all(test, unix))]
+use serialport::TTYPort;
+#[cfg(all(test, unix))]
+use std::io::{Read, Write};
+
+/// Device end of a pseudo-terminal pair and the port opened by the host end name
+#[cfg(all(test, unix))]
+fn pty_pair() -> (TTYPort, TTYPort, WakePort) {
+    let (device, host) = TTYPort::pair().unwrap();
+    let port = WakePort::open(&host.name().unwrap(), 115200).unwrap();
+    (device, host, port)
+}
+
+#[cfg(unix)]
+#[test]
+fn wake_port_test() {
+    let (mut device, _host, port) = pty_pair();
+    let mut port = port.with_timeout(Duration::from_millis(50));
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload([0x00, 0xC0, 0xeb])
+        .build()
+        .unwrap();
+    port.send(&wp).unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut rx = vec![0; encoded.len()];
+    device.read_exact(&mut rx).unwrap();
+    assert_eq!(rx, encoded);
+
+    // a frame split in two writes, a broken frame and one more frame
+    device.write_all(&encoded[..3]).unwrap();
+    device.flush().unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    device.write_all(&encoded[3..]).unwrap();
+    device.write_all(&[0xC0, 0x03, 0x01, 0xDB, 0x00]).unwrap();
+    device.write_all(&encoded).unwrap();
+    assert_eq!(port.recv(), Ok(wp.clone()));
+    assert!(matches!(port.recv(), Err(ClientError::Protocol(_))));
+    assert_eq!(port.recv(), Ok(wp.clone()));
+    assert_eq!(port.recv(), Err(ClientError::Timeout));
+}
+
+#[cfg(unix)]
+#[test]
+fn wake_port_transact_test() {
+    let (mut device, _host, port) = pty_pair();
+    let mut port = port.with_timeout(Duration::from_millis(500));
+    // echo device
+    let echo = std::thread::spawn(move || {
+        let mut reader = crate::PacketReader::new(device.try_clone_native().unwrap());
+        let p = loop {
+            match reader.read_packet() {
+                Err(crate::StreamError::Io(_)) => continue,
+                decoded => break decoded.unwrap(),
+            }
+        };
+        device.write_all(&p.encode().unwrap()).unwrap();
+        device.flush().unwrap();
+        device
+    });
+    let wp = Packet::builder()
+        .command(0x10)
+        .payload([2, 1])
+        .build()
+        .unwrap();
+    assert_eq!(port.transact(&wp), Ok(wp));
+    let _device = echo.join().unwrap();
+    port.clear().unwrap();
+    assert_eq!(port.recv(), Err(ClientError::Timeout));
+}
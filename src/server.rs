@@ -0,0 +1,419 @@
+//! Device-side command dispatcher: handlers registered per command reply to requests
+
+use core::fmt;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+use crate::frame::encode_to_vec_with;
+use crate::std_commands::{ErrReply, ECHO, ERR, INFO, NOP};
+use crate::{Address, Checksum, Config, Crc8, ErrorCode, Packet, StreamDecoder, Transport};
+
+/// How long [`WakeServer::serve`] waits for bytes before waiting again
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Receive buffer length of [`WakeServer::serve`]
+const RECV_BUF_LEN: usize = 64;
+
+/// Request handler of [`WakeServer`], closures `FnMut(&Packet) -> Result<Vec<u8>, ErrorCode>`
+/// are handlers too
+pub trait Handler: Send {
+    /// Handle a request
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - request packet
+    ///
+    /// # Output
+    ///
+    /// * `Result<Vec<u8>, ErrorCode>` - reply data, might be empty, or the code of the `ERR` reply
+    ///
+    fn handle(&mut self, request: &Packet) -> Result<Vec<u8>, ErrorCode>;
+}
+
+impl<F> Handler for F
+where
+    F: FnMut(&Packet) -> Result<Vec<u8>, ErrorCode> + Send,
+{
+    fn handle(&mut self, request: &Packet) -> Result<Vec<u8>, ErrorCode> {
+        self(request)
+    }
+}
+
+/// Device-side dispatcher: calls the handler of the request command and replies with its result
+///
+/// The reply has the command and the address of the request. A request is answered when:
+///
+/// * it has no address (point-to-point link)
+/// * its address is the server address (any address if it is not set)
+/// * a handler is registered for its address, so one server might simulate several devices
+///
+/// Requests to other addresses are ignored, broadcast requests (address 0) are handled
+/// without replying. An unknown command or a handler error is answered with the `ERR` reply:
+/// command 0x01 and the error code ([`ErrorCode::PARAMETER`] for unknown commands).
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{ErrorCode, Packet, WakeServer};
+///
+/// let mut server = WakeServer::new().with_address(0x12);
+/// server.register(None, 0x10, |request: &Packet| match request.data() {
+///     Some([relay @ 0..=3, mode]) => Ok(vec![*relay, *mode]),
+///     _ => Err(ErrorCode::PARAMETER),
+/// });
+///
+/// let request = "@12 #10 [02 01]".parse().unwrap();
+/// assert_eq!(server.handle(&request), Some(request));
+/// let request = "@12 #10 [07 01]".parse().unwrap();
+/// assert_eq!(format!("{:#}", server.handle(&request).unwrap()), "@12 #01 [04]");
+/// // other device
+/// assert_eq!(server.handle(&"@13 #10 [02 01]".parse().unwrap()), None);
+/// ```
+pub struct WakeServer<C = Crc8> {
+    address: Option<u8>,
+    handlers: HashMap<(Option<u8>, u8), Box<dyn Handler>>,
+    config: Config<C>,
+}
+
+impl WakeServer {
+    /// Create a CRC-8 server without handlers, it answers any address
+    pub fn new() -> Self {
+        Self::with_config(Config::new())
+    }
+}
+
+impl Default for WakeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Checksum> WakeServer<C> {
+    /// Create a server with the given link configuration
+    pub fn with_config(config: Config<C>) -> Self {
+        WakeServer {
+            address: None,
+            handlers: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Set the device address, requests to other addresses are ignored
+    pub fn with_address(mut self, address: u8) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Register a command handler, a registered one is replaced
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address, `None` for any served address
+    /// * `command` - command
+    /// * `handler` - closure returning the reply data or the error code
+    ///
+    pub fn register(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        handler: impl FnMut(&Packet) -> Result<Vec<u8>, ErrorCode> + Send + 'static,
+    ) -> &mut Self {
+        self.register_handler(address, command, handler)
+    }
+
+    /// Register a [`Handler`], see [`WakeServer::register`]
+    pub fn register_handler(
+        &mut self,
+        address: Option<u8>,
+        command: u8,
+        handler: impl Handler + 'static,
+    ) -> &mut Self {
+        self.handlers.insert((address, command), Box::new(handler));
+        self
+    }
+
+    /// Register handlers of the service commands for any served address, see
+    /// [`std_commands`](crate::std_commands)
+    ///
+    /// `NOP` is replied with no data, `ECHO` with the request data, `INFO` with the device
+    /// description and `ERR` (e.g. the host received a broken reply) with the `NONE` code.
+    ///
+    /// # Arguments
+    ///
+    /// * `info` - device description, e.g. `Relay shield v1.0`
+    ///
+    pub fn register_std_commands(&mut self, info: impl Into<String>) -> &mut Self {
+        let info = info.into().into_bytes();
+        let ack = ErrReply {
+            code: ErrorCode::NONE,
+        };
+        self.register(None, NOP, |_: &Packet| Ok(Vec::new()))
+            .register(None, ERR, move |_: &Packet| Ok(ack.payload().to_vec()))
+            .register(None, ECHO, |p: &Packet| {
+                Ok(p.data().unwrap_or_default().to_vec())
+            })
+            .register(None, INFO, move |_: &Packet| Ok(info.clone()))
+    }
+
+    /// Whether requests to the address are answered
+    fn serves(&self, address: u8) -> bool {
+        self.address.is_none()
+            || self.address == Some(address)
+            || self.handlers.keys().any(|(a, _)| *a == Some(address))
+    }
+
+    /// Handle a request
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - request packet
+    ///
+    /// # Output
+    ///
+    /// * `Option<Packet>` - reply, `None` for broadcast requests and requests to other addresses
+    ///
+    pub fn handle(&mut self, request: &Packet) -> Option<Packet> {
+        let address = request.address.map(u8::from);
+        let broadcast = request.address == Some(Address::BROADCAST);
+        if !broadcast && address.is_some_and(|a| !self.serves(a)) {
+            return None;
+        }
+        // the handler of the address first, then the one for any address
+        let command = request.command.get();
+        let key = match address {
+            Some(a) if self.handlers.contains_key(&(Some(a), command)) => (Some(a), command),
+            _ => (None, command),
+        };
+        let result = match self.handlers.get_mut(&key) {
+            Some(handler) => handler.handle(request),
+            None => Err(ErrorCode::PARAMETER),
+        };
+        if broadcast {
+            return None;
+        }
+        let max_len = self.config.max_data_len();
+        let (command, data) = match result {
+            Ok(data) if data.len() > max_len => (ERR, vec![ErrorCode::TX.0]),
+            Ok(data) => (command, data),
+            Err(code) => (ERR, vec![code.0]),
+        };
+        Some(Packet {
+            address: request.address,
+            command: command.try_into().ok()?,
+            // the data length is checked against the link maximum above
+            data,
+        })
+    }
+
+    /// Answer requests until the link is closed
+    ///
+    /// Broken frames are skipped, read timeouts are waited out.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - link to the host
+    ///
+    /// # Output
+    ///
+    /// * `io::Result<()>` - `Ok` when the link is closed or an I/O error
+    ///
+    pub fn serve<T: Transport>(&mut self, transport: &mut T) -> io::Result<()> {
+        let mut decoder = StreamDecoder::with_config(self.config);
+        let mut buf = [0u8; RECV_BUF_LEN];
+        let mut tx = Vec::new();
+        loop {
+            let n = match transport.recv(&mut buf, Instant::now() + POLL_INTERVAL) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            for request in decoder.feed(&buf[..n]).filter_map(Result::ok) {
+                if let Some(reply) = self.handle(&request) {
+                    let data = reply.data().unwrap_or_default();
+                    tx.clear();
+                    // the reply data length is checked by `handle`
+                    encode_to_vec_with(
+                        reply.address.map(u8::from),
+                        reply.command.get(),
+                        data,
+                        &mut tx,
+                        &self.config,
+                    )
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                    transport.send(&tx)?;
+                }
+            }
+        }
+    }
+}
+
+impl<C: Checksum> fmt::Debug for WakeServer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WakeServer")
+            .field("address", &self.address)
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Host simulator: sends the request frames a few bytes at a time and collects the replies
+#[cfg(test)]
+struct TestHost {
+    rx: Vec<u8>,
+    replies: Vec<u8>,
+}
+
+#[cfg(test)]
+impl Transport for TestHost {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.replies.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8], _deadline: Instant) -> io::Result<usize> {
+        let n = self.rx.len().min(buf.len()).min(3);
+        buf[..n].copy_from_slice(&self.rx[..n]);
+        self.rx.drain(..n);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+use crate::Encode;
+
+/// Relay board: 4 relays, `[relay, mode]` sets a relay and replies with its state
+#[cfg(test)]
+struct Relays([u8; 4]);
+
+#[cfg(test)]
+impl Handler for Relays {
+    fn handle(&mut self, request: &Packet) -> Result<Vec<u8>, ErrorCode> {
+        match request.data() {
+            Some([relay @ 0..=3, mode]) => {
+                self.0[*relay as usize] = *mode;
+                Ok(vec![*relay, *mode])
+            }
+            _ => Err(ErrorCode::PARAMETER),
+        }
+    }
+}
+
+#[test]
+fn wake_server_test() {
+    let mut server = WakeServer::new().with_address(0x12);
+    server
+        .register(None, 0x03, |_: &Packet| Ok(b"relay shield".to_vec()))
+        .register(None, 0x04, |_: &Packet| Err(ErrorCode::BUSY))
+        .register(Some(0x13), 0x03, |_: &Packet| Ok(b"sensor".to_vec()))
+        .register(None, 0x05, |_: &Packet| Ok(vec![0; 300]))
+        .register_handler(None, 0x10, Relays([0; 4]));
+    let reply = |server: &mut WakeServer, request: &str| {
+        server
+            .handle(&request.parse().unwrap())
+            .map(|p| format!("{:#}", p))
+    };
+    assert_eq!(
+        reply(&mut server, "@12 #03"),
+        Some("@12 #03 [72 65 6c 61 79 20 73 68 69 65 6c 64]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@13 #03"),
+        Some("@13 #03 [73 65 6e 73 6f 72]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "#10 [02 01]"),
+        Some("#10 [02 01]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@12 #10 [09 01]"),
+        Some("@12 #01 [04]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@12 #04"),
+        Some("@12 #01 [02]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@12 #05"),
+        Some("@12 #01 [01]".to_string())
+    );
+    // unknown command
+    assert_eq!(
+        reply(&mut server, "@13 #7f"),
+        Some("@13 #01 [04]".to_string())
+    );
+    // other address and broadcast
+    assert_eq!(reply(&mut server, "@14 #03"), None);
+    assert_eq!(reply(&mut server, "@00 #10 [03 01]"), None);
+    assert_eq!(
+        reply(&mut server, "#10 [03 00]"),
+        Some("#10 [03 00]".to_string())
+    );
+
+    // service commands
+    server.register_std_commands("Relay shield v1.0");
+    assert_eq!(
+        reply(&mut server, "@12 #00 [01]"),
+        Some("@12 #00".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@12 #01 [01]"),
+        Some("@12 #01 [00]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "#02 [c0 01]"),
+        Some("#02 [c0 01]".to_string())
+    );
+    assert_eq!(
+        reply(&mut server, "@13 #03"),
+        Some("@13 #03 [73 65 6e 73 6f 72]".to_string())
+    );
+
+    // any address without the server address
+    let mut server = WakeServer::new();
+    server.register(None, 0x02, |p: &Packet| {
+        Ok(p.data().unwrap_or_default().to_vec())
+    });
+    assert_eq!(
+        reply(&mut server, "@55 #02 [01]"),
+        Some("@55 #02 [01]".to_string())
+    );
+}
+
+#[test]
+fn wake_server_serve_test() {
+    let mut server = WakeServer::new().with_address(0x12);
+    server.register(None, 0x02, |p: &Packet| {
+        Ok(p.data().unwrap_or_default().to_vec())
+    });
+    let packets: Vec<Packet> = ["@12 #02 [c0 db]", "@13 #02 [01]", "#7f", "@12 #02"]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    let mut rx = Vec::new();
+    for p in &packets {
+        rx.extend(p.encode().unwrap());
+    }
+    // a broken frame is skipped
+    rx.splice(0..0, [0xC0, 0x12, 0x02, 0x01, 0x00, 0x00]);
+    let mut host = TestHost {
+        rx,
+        replies: Vec::new(),
+    };
+    server.serve(&mut host).unwrap();
+    let replies: Vec<String> = crate::StreamDecoder::new()
+        .feed(&host.replies)
+        .map(|p| format!("{:#}", p.unwrap()))
+        .collect();
+    assert_eq!(replies, ["@12 #02 [c0 db]", "#01 [04]", "@12 #02"]);
+}
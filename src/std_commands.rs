@@ -0,0 +1,246 @@
+//! Service commands of the Wake specification: `NOP`, `ERR`, `ECHO` and `INFO`
+//!
+//! The specification reserves the low command codes for them, application commands start
+//! after [`INFO`]. Requests and replies are typed by the structs of this module, e.g.
+//! [`ErrReply::parse`] reads the error code of an `ERR` reply.
+//!
+//! * host side - `WakeClient::ping`, `WakeClient::info`, `ERR` replies end requests with
+//!   `ClientError::Device`
+//! * device side - `WakeServer::register_std_commands` and [`handle_nop`], [`handle_echo`],
+//!   [`handle_err`], [`write_info`] for [`Dispatcher`](crate::Dispatcher) tables
+//!
+//! # Example
+//!
+//! ```
+//! extern crate wake_rs;
+//! use wake_rs::std_commands::{self, ErrReply, InfoReply};
+//! use wake_rs::ErrorCode;
+//!
+//! assert_eq!(ErrReply::parse(&[0x02]), Some(ErrReply { code: ErrorCode::BUSY }));
+//! let info = InfoReply::parse(b"Relay shield v1.0\0").unwrap();
+//! assert_eq!(info.text, "Relay shield v1.0");
+//!
+//! let mut reply = [0u8; wake_rs::DATA_MAX_LEN];
+//! let n = std_commands::write_info("Relay shield v1.0", &mut reply).unwrap();
+//! assert_eq!(InfoReply::parse(&reply[..n]), Some(info));
+//! ```
+
+use crate::ErrorCode;
+#[cfg(feature = "std")]
+use crate::{ClientError, Packet};
+
+/// No operation, the device replies with no data
+pub const NOP: u8 = 0x00;
+
+/// Error reply, its data is the error code
+pub const ERR: u8 = 0x01;
+
+/// Echo, the device replies with the request data
+pub const ECHO: u8 = 0x02;
+
+/// Device information, the device replies with its description
+pub const INFO: u8 = 0x03;
+
+/// `NOP` request and reply
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Nop;
+
+impl Nop {
+    /// Command of the request and the reply
+    pub const COMMAND: u8 = NOP;
+
+    /// Parse the data, `None` if there is any
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.is_empty() {
+            Some(Nop)
+        } else {
+            None
+        }
+    }
+
+    /// Data of the request or the reply
+    pub fn payload(&self) -> &'static [u8] {
+        &[]
+    }
+}
+
+/// `ERR` reply
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ErrReply {
+    /// Error code
+    pub code: ErrorCode,
+}
+
+impl ErrReply {
+    /// Command of the reply
+    pub const COMMAND: u8 = ERR;
+
+    /// Parse the data, `None` if it isn't a single error code
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        match data {
+            [code] => Some(ErrReply {
+                code: ErrorCode(*code),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Data of the reply
+    pub fn payload(&self) -> [u8; 1] {
+        [self.code.0]
+    }
+}
+
+/// `ECHO` request and reply
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Echo<'a> {
+    /// Data sent back by the device
+    pub data: &'a [u8],
+}
+
+impl<'a> Echo<'a> {
+    /// Command of the request and the reply
+    pub const COMMAND: u8 = ECHO;
+
+    /// Parse the data, any data is an echo
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        Some(Echo { data })
+    }
+
+    /// Data of the request or the reply
+    pub fn payload(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// `INFO` reply, the request has no data
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct InfoReply<'a> {
+    /// Device description, e.g. `Relay shield v1.0`
+    pub text: &'a str,
+}
+
+impl<'a> InfoReply<'a> {
+    /// Command of the reply
+    pub const COMMAND: u8 = INFO;
+
+    /// Parse the data, trailing NUL characters of C strings are dropped
+    ///
+    /// # Output
+    ///
+    /// * `Option<InfoReply>` - description, `None` if the data is not UTF-8
+    ///
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let text = core::str::from_utf8(data).ok()?;
+        Some(InfoReply {
+            text: text.trim_end_matches('\0'),
+        })
+    }
+
+    /// Data of the reply
+    pub fn payload(&self) -> &'a [u8] {
+        self.text.as_bytes()
+    }
+}
+
+/// `NOP` handler of a [`Dispatcher`](crate::Dispatcher) table: replies with no data
+pub fn handle_nop(_data: &[u8], _reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    Ok(0)
+}
+
+/// `ECHO` handler of a [`Dispatcher`](crate::Dispatcher) table: replies with the request data
+pub fn handle_echo(data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    let reply = reply.get_mut(..data.len()).ok_or(ErrorCode::TX)?;
+    reply.copy_from_slice(data);
+    Ok(data.len())
+}
+
+/// `ERR` handler of a [`Dispatcher`](crate::Dispatcher) table: an `ERR` request (e.g. the host
+/// received a broken reply) is acknowledged with the `NONE` code
+pub fn handle_err(_data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    let ack = ErrReply {
+        code: ErrorCode::NONE,
+    };
+    let reply = reply.get_mut(..1).ok_or(ErrorCode::TX)?;
+    reply.copy_from_slice(&ack.payload());
+    Ok(1)
+}
+
+/// Write the `INFO` reply in a [`Dispatcher`](crate::Dispatcher) handler
+///
+/// # Arguments
+///
+/// * `text` - device description
+/// * `reply` - reply data buffer
+///
+/// # Output
+///
+/// * `Result<usize, ErrorCode>` - reply data length, `TX` if the text doesn't fit
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::{std_commands, CommandFn, ErrorCode};
+///
+/// fn info(_data: &[u8], reply: &mut [u8]) -> Result<usize, ErrorCode> {
+///     std_commands::write_info("Relay shield v1.0", reply)
+/// }
+///
+/// static COMMANDS: [(u8, CommandFn); 4] = [
+///     (std_commands::NOP, std_commands::handle_nop),
+///     (std_commands::ERR, std_commands::handle_err),
+///     (std_commands::ECHO, std_commands::handle_echo),
+///     (std_commands::INFO, info),
+/// ];
+/// ```
+pub fn write_info(text: &str, reply: &mut [u8]) -> Result<usize, ErrorCode> {
+    let data = InfoReply { text }.payload();
+    let reply = reply.get_mut(..data.len()).ok_or(ErrorCode::TX)?;
+    reply.copy_from_slice(data);
+    Ok(data.len())
+}
+
+/// Check the `ECHO` reply data against the request one
+#[cfg(feature = "std")]
+pub(crate) fn check_echo(payload: &[u8], reply: &Packet) -> Result<(), ClientError> {
+    let echo = Echo::parse(reply.data().unwrap_or_default());
+    if echo.is_some_and(|echo| echo.data == payload) {
+        Ok(())
+    } else {
+        Err(ClientError::InvalidReply)
+    }
+}
+
+/// Device description of the `INFO` reply
+#[cfg(feature = "std")]
+pub(crate) fn info_text(reply: &Packet) -> Result<String, ClientError> {
+    InfoReply::parse(reply.data().unwrap_or_default())
+        .map(|info| info.text.to_string())
+        .ok_or(ClientError::InvalidReply)
+}
+
+#[test]
+fn std_commands_test() {
+    assert_eq!(Nop::parse(&[]), Some(Nop));
+    assert_eq!(Nop::parse(&[0]), None);
+    assert_eq!(ErrReply::parse(&[]), None);
+    assert_eq!(ErrReply::parse(&[4, 0]), None);
+    assert_eq!(
+        ErrReply::parse(&[4]).map(|e| e.code),
+        Some(ErrorCode::PARAMETER)
+    );
+    assert_eq!(Echo::parse(&[1, 2]).unwrap().payload(), &[1, 2]);
+    assert_eq!(InfoReply::parse(&[0xff]), None);
+    assert_eq!(InfoReply::parse(b"\0\0").unwrap().text, "");
+
+    let mut reply = [0u8; 4];
+    assert_eq!(handle_nop(&[1], &mut reply), Ok(0));
+    assert_eq!(handle_echo(&[1, 2], &mut reply), Ok(2));
+    assert_eq!(&reply[..2], &[1, 2]);
+    assert_eq!(handle_echo(&[1; 5], &mut reply), Err(ErrorCode::TX));
+    assert_eq!(handle_err(&[], &mut reply), Ok(1));
+    assert_eq!(reply[0], ErrorCode::NONE.0);
+    assert_eq!(write_info("v1.0", &mut reply), Ok(4));
+    assert_eq!(write_info("v1.0.1", &mut reply), Err(ErrorCode::TX));
+}
@@ -0,0 +1,419 @@
+//! Incremental Wake decoder for byte streams (UART, sockets, pipes, etc.)
+
+use crate::packet_ref::unpack;
+#[cfg(feature = "std")]
+use crate::Packet;
+use crate::{
+    Checksum, Config, Crc8, PacketRef, WakeError, ADDR_MASK, FEND, FESC, PACKET_MAX_LEN, TFEND,
+    TFESC,
+};
+
+/// Stream decoder state
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Waiting for a start symbol (FEND), all other bytes are skipped
+    Idle,
+    /// Receiving a frame
+    Frame,
+    /// Receiving a frame, previous byte was FESC
+    Escape,
+}
+
+/// Stateful decoder which is fed bytes one at a time or in chunks
+///
+/// The decoder keeps a destuffed copy of the current frame and emits a packet as soon
+/// as the CRC byte arrives. After any error it drops the current frame and
+/// resynchronizes on the next FEND.
+///
+/// The link configuration is CRC-8 and `DATA_MAX_LEN` bytes of data by default, use
+/// [`StreamDecoder::with_config`] for another one. Frames with too long data are rejected
+/// as soon as their header arrives.
+///
+/// Without `std` use [`StreamDecoder::push_ref`], which borrows the data load from the decoder.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::StreamDecoder;
+///
+/// let mut decoder = StreamDecoder::new();
+/// // noise, first half of the frame, second half of the frame
+/// let chunks: [&[u8]; 3] = [&[0x12, 0x34], &[0xC0, 0x03, 0x05, 1, 2], &[3, 4, 5, 0x6b]];
+/// for chunk in chunks {
+///     for &byte in chunk {
+///         if let Some(decoded) = decoder.push_ref(byte) {
+///             println!("Decoded packet\t: {}", decoded.unwrap());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamDecoder<C = Crc8> {
+    state: State,
+    frame: [u8; PACKET_MAX_LEN],
+    len: usize,
+    /// Offset of the last received byte from FEND, in stuffed bytes
+    offset: usize,
+    config: Config<C>,
+}
+
+impl<C: Checksum> Default for StreamDecoder<C> {
+    fn default() -> Self {
+        Self::with_config(Config::default())
+    }
+}
+
+impl StreamDecoder {
+    /// Create a new CRC-8 decoder waiting for a start symbol
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Checksum> StreamDecoder<C> {
+    /// Create a new decoder with the given link configuration
+    pub fn with_config(config: Config<C>) -> Self {
+        StreamDecoder {
+            state: State::Idle,
+            frame: [0; PACKET_MAX_LEN],
+            len: 0,
+            offset: 0,
+            config,
+        }
+    }
+
+    /// Drop the current frame and wait for the next start symbol
+    pub fn reset(&mut self) {
+        self.state = State::Idle;
+        self.len = 0;
+        self.offset = 0;
+    }
+
+    /// Process one byte
+    ///
+    /// # Arguments
+    ///
+    /// * `byte` - received byte
+    ///
+    /// # Output
+    ///
+    /// * `Option<Result<Packet, WakeError>>` - decoded packet or error, `None` if the frame is not complete yet
+    ///
+    #[cfg(feature = "std")]
+    pub fn push(&mut self, byte: u8) -> Option<Result<Packet, WakeError>> {
+        self.push_ref(byte)
+            .map(|decoded| decoded.and_then(Packet::try_from))
+    }
+
+    /// Process one byte, the decoded packet borrows its data from the decoder
+    ///
+    /// # Arguments
+    ///
+    /// * `byte` - received byte
+    ///
+    /// # Output
+    ///
+    /// * `Option<Result<PacketRef, WakeError>>` - decoded packet or error, `None` if the frame is not complete yet
+    ///
+    pub fn push_ref(&mut self, byte: u8) -> Option<Result<PacketRef<'_>, WakeError>> {
+        if self.state != State::Idle {
+            self.offset += 1;
+        }
+        let data = match (self.state, byte) {
+            (_, FEND) => {
+                // A start symbol always begins a new frame. Repeated FENDs are allowed,
+                // but a frame interrupted by FEND is reported.
+                let interrupted = self.state != State::Idle && self.len > 1;
+                let err = self.broken();
+                self.state = State::Frame;
+                self.frame[0] = FEND;
+                self.len = 1;
+                self.offset = 0;
+                return if interrupted { Some(Err(err)) } else { None };
+            }
+            (State::Idle, _) => return None,
+            (State::Frame, FESC) => {
+                self.state = State::Escape;
+                return None;
+            }
+            (State::Frame, b) => b,
+            (State::Escape, TFEND) => FEND,
+            (State::Escape, TFESC) => FESC,
+            (State::Escape, b) => {
+                let offset = self.offset;
+                self.reset();
+                return Some(Err(WakeError::DestuffingFailed { offset, byte: b }));
+            }
+        };
+        self.state = State::Frame;
+        match self.frame.get_mut(self.len) {
+            Some(slot) => *slot = data,
+            None => {
+                let err = self.broken();
+                self.reset();
+                return Some(Err(err));
+            }
+        }
+        self.len += 1;
+
+        match self.header_len() {
+            Some(n) if self.len == n && self.frame[n - 1] as usize > self.config.max_data_len() => {
+                let len = self.frame[n - 1] as usize;
+                self.reset();
+                Some(Err(WakeError::TooLongData {
+                    len,
+                    max: self.config.max_data_len(),
+                }))
+            }
+            Some(n) if self.len == n + self.frame[n - 1] as usize + C::LEN => {
+                let end = self.len;
+                self.reset();
+                Some(unpack(&self.frame[..end], &self.config))
+            }
+            _ => None,
+        }
+    }
+
+    /// Process a chunk of bytes
+    ///
+    /// Bytes are consumed lazily while the returned iterator is advanced.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - received bytes
+    ///
+    /// # Output
+    ///
+    /// * `Feed` - iterator over decoded packets and errors
+    ///
+    #[cfg(feature = "std")]
+    pub fn feed<'a>(&'a mut self, bytes: &'a [u8]) -> Feed<'a, C> {
+        Feed {
+            decoder: self,
+            bytes: bytes.iter(),
+        }
+    }
+
+    /// Error for the current frame if it ends here
+    fn broken(&self) -> WakeError {
+        match self.header_len() {
+            None => WakeError::TooShortPacket { len: self.offset },
+            Some(n) => WakeError::WrongPacketLength {
+                declared: self.frame[n - 1] as usize,
+                actual: (self.len - n).saturating_sub(C::LEN),
+            },
+        }
+    }
+
+    /// Length of the frame header (FEND, address, command, data length), if it is received
+    fn header_len(&self) -> Option<usize> {
+        let n = match self.frame[1] & ADDR_MASK {
+            0 => 3,
+            _ => 4,
+        };
+        if self.len >= n {
+            Some(n)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over packets decoded from a chunk of bytes, see [`StreamDecoder::feed`]
+#[cfg(feature = "std")]
+#[must_use = "bytes are consumed only while the iterator is advanced"]
+pub struct Feed<'a, C = Crc8> {
+    decoder: &'a mut StreamDecoder<C>,
+    bytes: std::slice::Iter<'a, u8>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, C: Checksum> Iterator for Feed<'a, C> {
+    type Item = Result<Packet, WakeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            if let Some(decoded) = self.decoder.push(*byte) {
+                return Some(decoded);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+use crate::{Crc16, Encode, NoCrc};
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_byte_by_byte_test() {
+    let wp = Packet::builder()
+        .address(0x40)
+        .command(0x40)
+        .payload(vec![FEND, FESC, 1, 2, 3])
+        .build()
+        .unwrap();
+    let encoded = wp.encode().unwrap();
+    let mut decoder = StreamDecoder::new();
+    let (last, head) = encoded.split_last().unwrap();
+    for b in head {
+        assert!(decoder.push(*b).is_none());
+    }
+    assert_eq!(decoder.push(*last), Some(Ok(wp)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_chunks_test() {
+    let mut stream = vec![0x00, 0x11, TFEND]; // noise
+    for cmd in 0..3 {
+        let wp = Packet::builder().command(cmd).build().unwrap();
+        stream.extend(wp.encode().unwrap());
+    }
+    let mut decoder = StreamDecoder::new();
+    let mut commands = vec![];
+    for chunk in stream.chunks(4) {
+        for decoded in decoder.feed(chunk) {
+            commands.push(decoded.unwrap().command);
+        }
+    }
+    assert_eq!(commands, vec![0, 1, 2]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_resync_test() {
+    let good = vec![FEND, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6b];
+    let wrong_crc = vec![FEND, 0x03, 0x05, 1, 2, 3, 4, 5, 0x6c];
+    let wrong_stuffing = vec![FEND, 0x03, 0x01, FESC, 0x00];
+    let interrupted = vec![FEND, 0x03, 0x05, 1, 2];
+    let mut decoder = StreamDecoder::new();
+
+    let mut stream = vec![];
+    stream.extend(&wrong_crc);
+    stream.extend(&good);
+    stream.extend(&wrong_stuffing);
+    stream.extend(&good[1..]); // no start symbol after an error, should be skipped
+    stream.extend(&interrupted);
+    stream.extend(&good);
+    stream.extend(&[FEND, 0x03]);
+    stream.extend(&good);
+    let decoded: Vec<_> = decoder
+        .feed(&stream)
+        .map(|d| d.map(|p| p.command.get()))
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![
+            Err(WakeError::WrongPacketCrc {
+                expected: 0x6b,
+                received: 0x6c
+            }),
+            Ok(0x03),
+            Err(WakeError::DestuffingFailed {
+                offset: 4,
+                byte: 0x00
+            }),
+            Err(WakeError::WrongPacketLength {
+                declared: 5,
+                actual: 1
+            }),
+            Ok(0x03),
+            Err(WakeError::TooShortPacket { len: 2 }),
+            Ok(0x03),
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_checksum_test() {
+    let wp = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload(vec![0x00, 0xeb])
+        .build()
+        .unwrap();
+    let mut crc16 = StreamDecoder::<Crc16>::default();
+    let encoded = wp.encode_with(&Config::<Crc16>::default()).unwrap();
+    let decoded: Vec<_> = crc16.feed(&encoded).collect();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].as_ref().unwrap().data, wp.data);
+
+    let mut no_crc = StreamDecoder::<NoCrc>::default();
+    let encoded = wp.encode_with(&Config::<NoCrc>::default()).unwrap();
+    let decoded: Vec<_> = no_crc.feed(&encoded).collect();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].as_ref().unwrap().data, wp.data);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_max_data_len_test() {
+    let long = Packet::builder()
+        .command(1)
+        .payload(vec![0; 3])
+        .build()
+        .unwrap();
+    let short = Packet::builder()
+        .command(2)
+        .payload(vec![0; 2])
+        .build()
+        .unwrap();
+    let mut stream = long.encode().unwrap();
+    stream.extend(short.encode().unwrap());
+    let mut decoder = StreamDecoder::with_config(Config::new().with_max_data_len(2));
+    let decoded: Vec<_> = decoder
+        .feed(&stream)
+        .map(|d| d.map(|p| p.command.get()))
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![Err(WakeError::TooLongData { len: 3, max: 2 }), Ok(2)]
+    );
+}
+
+#[test]
+fn stream_push_ref_resync_test() {
+    let mut decoder = StreamDecoder::new();
+    let mut stream = [0u8; 64];
+    let mut n = 0;
+    // noise, a broken frame, an interrupted one and a good one
+    for chunk in [
+        &[0x00, 0x11][..],
+        &[FEND, 0x03, 0x01, FESC, 0x00],
+        &[FEND, 0x03, 0x05, 1, 2],
+    ] {
+        stream[n..n + chunk.len()].copy_from_slice(chunk);
+        n += chunk.len();
+    }
+    n += crate::encode_into(Some(0x12), 3, &[FEND], &mut stream[n..]).unwrap();
+    let mut errors = 0;
+    let mut commands = 0;
+    for &b in &stream[..n] {
+        match decoder.push_ref(b) {
+            Some(Ok(p)) => {
+                assert_eq!((p.address, p.command, p.data), (Some(0x12), 3, &[FEND][..]));
+                commands += 1;
+            }
+            Some(Err(_)) => errors += 1,
+            None => {}
+        }
+    }
+    assert_eq!((errors, commands), (2, 1));
+}
+
+#[test]
+fn stream_push_ref_test() {
+    let mut decoder = StreamDecoder::new();
+    let frame = [0x12, FEND, 0x92, 0x03, 0x02, 0x00, 0xeb, 114];
+    let (last, head) = frame.split_last().unwrap();
+    for b in head {
+        assert!(decoder.push_ref(*b).is_none());
+    }
+    let decoded = decoder.push_ref(*last).unwrap().unwrap();
+    assert_eq!(decoded.address, Some(0x12));
+    assert_eq!(decoded.command, 3);
+    assert_eq!(decoded.data, &[0x00, 0xeb]);
+}
@@ -0,0 +1,250 @@
+//! One-line text notation of packets, e.g. `@12 #03 [00 eb]`
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{Address, Command, Packet, WakeError, DATA_MAX_LEN};
+
+/// What is wrong with a packet text, see [`ParsePacketError`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// Unexpected character
+    UnexpectedChar(char),
+    /// Text ends in the middle of a field
+    UnexpectedEnd,
+    /// Command (`#03`) is missing
+    NoCommand,
+    /// Value is out of range: `WrongAddrRange`, `WrongCmdRange` or `TooLongData`
+    Invalid(WakeError),
+}
+
+/// Packet text parse error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParsePacketError {
+    /// Column of the offending character, starting from 1
+    pub column: usize,
+    /// What is wrong
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParsePacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: ", self.column)?;
+        match self.kind {
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::UnexpectedEnd => f.write_str("unexpected end of text"),
+            ParseErrorKind::NoCommand => f.write_str("command is missing"),
+            ParseErrorKind::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParsePacketError {}
+
+/// Packet text being parsed
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Skip `c` if it is the next character
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error_at(&self, pos: usize, kind: ParseErrorKind) -> ParsePacketError {
+        ParsePacketError {
+            column: pos + 1,
+            kind,
+        }
+    }
+
+    /// Error for the next character
+    fn unexpected(&self) -> ParsePacketError {
+        let kind = match self.peek() {
+            Some(c) => ParseErrorKind::UnexpectedChar(c),
+            None => ParseErrorKind::UnexpectedEnd,
+        };
+        self.error_at(self.pos, kind)
+    }
+
+    /// Hex byte of up to 2 digits
+    fn hex_byte(&mut self, min_digits: usize) -> Result<u8, ParsePacketError> {
+        let mut value = 0u8;
+        let mut digits = 0;
+        while digits < 2 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(d) => value = value << 4 | d as u8,
+                None => break,
+            }
+            self.pos += 1;
+            digits += 1;
+        }
+        if digits < min_digits {
+            return Err(self.unexpected());
+        }
+        Ok(value)
+    }
+}
+
+/// Parse the text notation produced by `{:#}`
+///
+/// `@` is followed by the address (optional), `#` by the command, data bytes are enclosed
+/// in brackets (optional). Values are hex, bytes in brackets have 2 digits and might be
+/// separated by whitespace.
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::Packet;
+///
+/// let p: Packet = "@12 #03 [00 eb]".parse().unwrap();
+/// assert_eq!(p, Packet::builder().address(0x12).command(3).payload([0x00, 0xeb]).build().unwrap());
+/// assert_eq!(format!("{:#}", p), "@12 #03 [00 eb]");
+/// let e = "@12 #3g".parse::<Packet>().unwrap_err();
+/// assert_eq!(e.to_string(), "column 7: unexpected character 'g'");
+/// ```
+impl FromStr for Packet {
+    type Err = ParsePacketError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut p = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+        let mut packet = Packet::default();
+        // 1: Address
+        p.skip_whitespace();
+        if p.eat('@') {
+            let pos = p.pos;
+            let address = p.hex_byte(1)?;
+            let address =
+                Address::new(address).map_err(|e| p.error_at(pos, ParseErrorKind::Invalid(e)))?;
+            packet.address = Some(address);
+            p.skip_whitespace();
+        }
+        // 2: Command
+        if p.peek().is_none() {
+            return Err(p.error_at(p.pos, ParseErrorKind::NoCommand));
+        }
+        if !p.eat('#') {
+            return Err(p.unexpected());
+        }
+        let pos = p.pos;
+        let command = p.hex_byte(1)?;
+        packet.command =
+            Command::new(command).map_err(|e| p.error_at(pos, ParseErrorKind::Invalid(e)))?;
+        p.skip_whitespace();
+        // 3: Data
+        if p.eat('[') {
+            let mut data = Vec::new();
+            loop {
+                p.skip_whitespace();
+                if p.eat(']') {
+                    break;
+                }
+                if data.len() == DATA_MAX_LEN && p.peek().is_some() {
+                    let e = WakeError::TooLongData {
+                        len: DATA_MAX_LEN + 1,
+                        max: DATA_MAX_LEN,
+                    };
+                    return Err(p.error_at(p.pos, ParseErrorKind::Invalid(e)));
+                }
+                data.push(p.hex_byte(2)?);
+            }
+            packet.data = data;
+            p.skip_whitespace();
+        }
+        // 4: Nothing else is allowed
+        match p.peek() {
+            Some(_) => Err(p.unexpected()),
+            None => Ok(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+use rand::Rng;
+
+#[test]
+fn text_test() {
+    let p = Packet::builder()
+        .address(0x12)
+        .command(3)
+        .payload([0x00, 0xeb])
+        .build()
+        .unwrap();
+    assert_eq!(format!("{:#}", p), "@12 #03 [00 eb]");
+    for text in ["@12 #03 [00 eb]", " @12#3[00EB] ", "@12\t#03 [ 00 eb ]"] {
+        assert_eq!(text.parse(), Ok(p.clone()), "{}", text);
+    }
+    let p = Packet::builder().command(0x7f).build().unwrap();
+    assert_eq!(format!("{:#}", p), "#7f");
+    assert_eq!("#7f []".parse(), Ok(p));
+
+    let error = |text: &str| text.parse::<Packet>().unwrap_err();
+    let e = error("@12 #03 [00 e]");
+    assert_eq!(e.column, 14);
+    assert_eq!(e.kind, ParseErrorKind::UnexpectedChar(']'));
+    assert_eq!(
+        error("@12 #03 [00"),
+        ParsePacketError {
+            column: 12,
+            kind: ParseErrorKind::UnexpectedEnd
+        }
+    );
+    assert_eq!(error("@12 ").kind, ParseErrorKind::NoCommand);
+    assert_eq!(error("").column, 1);
+    assert_eq!(error("#03 x").kind, ParseErrorKind::UnexpectedChar('x'));
+    assert_eq!(error("@123 #03").column, 4);
+    let e = error("#03 @12");
+    assert_eq!((e.column, e.kind), (5, ParseErrorKind::UnexpectedChar('@')));
+    let e = error("@80 #03");
+    assert_eq!(e.column, 2);
+    assert_eq!(
+        e.to_string(),
+        "column 2: address 0x80 is out of range [0 - 127]"
+    );
+    let e = error("#ff");
+    assert_eq!(
+        e.kind,
+        ParseErrorKind::Invalid(WakeError::WrongCmdRange { command: 0xff })
+    );
+    let long = format!("#01 [{}]", "00".repeat(DATA_MAX_LEN + 1));
+    assert_eq!(error(&long).column, 6 + 2 * DATA_MAX_LEN);
+    let longest = format!("#01 [{}]", "00".repeat(DATA_MAX_LEN));
+    assert!(longest.parse::<Packet>().is_ok());
+}
+
+#[test]
+fn random_text_test() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..10_000 {
+        let n = rng.gen_range(0..=DATA_MAX_LEN);
+        let mut builder = Packet::builder()
+            .command(rng.gen_range(0..0x80))
+            .payload((0..n).map(|_| rng.gen()).collect::<Vec<u8>>());
+        if rng.gen_bool(0.5) {
+            builder = builder.address(rng.gen_range(0..0x80));
+        }
+        let p = builder.build().unwrap();
+        assert_eq!(format!("{:#}", p).parse(), Ok(p));
+    }
+}
@@ -0,0 +1,234 @@
+//! Checked packet fields: device address and command, error codes of `ERR` replies
+
+use core::fmt;
+
+use crate::WakeError;
+
+/// Device address [0 - 127]
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::Address;
+///
+/// let address = Address::new(0x12).unwrap();
+/// assert_eq!(address.get(), 0x12);
+/// assert!(Address::new(0x80).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct Address(u8);
+
+impl Address {
+    /// Largest address
+    pub const MAX: Address = Address(0x7f);
+
+    /// Broadcast address, devices handle such requests without replying
+    pub const BROADCAST: Address = Address(0);
+
+    /// Create an address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - device address [0 - 127]
+    ///
+    /// # Output
+    ///
+    /// * `Result<Address, WakeError>` - address or `WrongAddrRange`
+    ///
+    pub const fn new(address: u8) -> Result<Self, WakeError> {
+        if address > Self::MAX.0 {
+            Err(WakeError::WrongAddrRange { address })
+        } else {
+            Ok(Address(address))
+        }
+    }
+
+    /// Address value
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Address {
+    type Error = WakeError;
+
+    fn try_from(address: u8) -> Result<Self, Self::Error> {
+        Address::new(address)
+    }
+}
+
+impl From<Address> for u8 {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl PartialEq<u8> for Address {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:02X}", self.0)
+    }
+}
+
+/// Command [0 - 127]
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::Command;
+///
+/// let command = Command::new(3).unwrap();
+/// assert_eq!(u8::from(command), 3);
+/// assert!(Command::try_from(0xC0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct Command(u8);
+
+impl Command {
+    /// Largest command
+    pub const MAX: Command = Command(0x7f);
+
+    /// Create a command
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - command [0 - 127]
+    ///
+    /// # Output
+    ///
+    /// * `Result<Command, WakeError>` - command or `WrongCmdRange`
+    ///
+    pub const fn new(command: u8) -> Result<Self, WakeError> {
+        if command > Self::MAX.0 {
+            Err(WakeError::WrongCmdRange { command })
+        } else {
+            Ok(Command(command))
+        }
+    }
+
+    /// Command value
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Command {
+    type Error = WakeError;
+
+    fn try_from(command: u8) -> Result<Self, Self::Error> {
+        Command::new(command)
+    }
+}
+
+impl From<Command> for u8 {
+    fn from(command: Command) -> Self {
+        command.0
+    }
+}
+
+impl PartialEq<u8> for Command {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:02X}", self.0)
+    }
+}
+
+/// Error code of the `ERR` reply, codes of the Wake specification are provided as constants
+///
+/// # Example
+///
+/// ```
+/// extern crate wake_rs;
+/// use wake_rs::ErrorCode;
+///
+/// assert_eq!(ErrorCode::from(0x02), ErrorCode::BUSY);
+/// assert_eq!(ErrorCode::BUSY.to_string(), "device is busy");
+/// assert_eq!(ErrorCode(0x42).to_string(), "error 0x42");
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorCode(pub u8);
+
+impl ErrorCode {
+    /// No error
+    pub const NONE: ErrorCode = ErrorCode(0x00);
+    /// Receive/transmit error, e.g. a broken frame
+    pub const TX: ErrorCode = ErrorCode(0x01);
+    /// Device is busy
+    pub const BUSY: ErrorCode = ErrorCode(0x02);
+    /// Device is not ready
+    pub const NOT_READY: ErrorCode = ErrorCode(0x03);
+    /// Wrong parameter value, also sent for unknown commands
+    pub const PARAMETER: ErrorCode = ErrorCode(0x04);
+    /// No reply
+    pub const NO_REPLY: ErrorCode = ErrorCode(0x05);
+    /// No carrier
+    pub const NO_CARRIER: ErrorCode = ErrorCode(0x06);
+}
+
+impl From<u8> for ErrorCode {
+    fn from(code: u8) -> Self {
+        ErrorCode(code)
+    }
+}
+
+impl From<ErrorCode> for u8 {
+    fn from(code: ErrorCode) -> Self {
+        code.0
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCode::NONE => f.write_str("no error"),
+            ErrorCode::TX => f.write_str("receive/transmit error"),
+            ErrorCode::BUSY => f.write_str("device is busy"),
+            ErrorCode::NOT_READY => f.write_str("device is not ready"),
+            ErrorCode::PARAMETER => f.write_str("wrong parameter value"),
+            ErrorCode::NO_REPLY => f.write_str("no reply"),
+            ErrorCode::NO_CARRIER => f.write_str("no carrier"),
+            ErrorCode(code) => write!(f, "error 0x{:02X}", code),
+        }
+    }
+}
+
+#[test]
+fn address_command_test() {
+    assert_eq!(Address::new(0), Ok(Address(0)));
+    assert_eq!(Address::try_from(0x7f), Ok(Address::MAX));
+    assert_eq!(
+        Address::new(0x80),
+        Err(WakeError::WrongAddrRange { address: 0x80 })
+    );
+    assert_eq!(Command::new(0x7f).map(u8::from), Ok(0x7f));
+    assert_eq!(
+        Command::try_from(0xff),
+        Err(WakeError::WrongCmdRange { command: 0xff })
+    );
+    assert_eq!(Command::new(3).unwrap(), 3);
+    #[cfg(feature = "std")]
+    assert_eq!(Address::new(0x12).unwrap().to_string(), "0x12");
+}
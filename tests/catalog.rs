@@ -0,0 +1,65 @@
+//! The installed command catalog is global, it is checked in its own test binary so no other
+//! test prints packets meanwhile
+
+extern crate wake_rs;
+
+use wake_rs::{CommandCatalog, Packet, PacketFormatter, PacketRef};
+
+#[test]
+fn global_catalog_test() {
+    let mut catalog = CommandCatalog::new();
+    catalog
+        .register(None, 0x02, "GET_INFO")
+        .register_with_decoder(Some(0x12), 0x10, "SET_RELAY", |data| match data {
+            [relay, mode] => Some(format!("relay={} mode={}", relay, mode)),
+            _ => None,
+        });
+    CommandCatalog::set_global(catalog);
+    assert_eq!(
+        CommandCatalog::global().unwrap().name(None, 0x02),
+        Some("GET_INFO")
+    );
+
+    let p = Packet::builder()
+        .address(0x12)
+        .command(0x10)
+        .payload([2, 1])
+        .build()
+        .unwrap();
+    assert_eq!(
+        p.to_string(),
+        "ADDR: 0x12\nCMD:  0x10 SET_RELAY relay=2 mode=1\n"
+    );
+    let p = PacketRef {
+        address: None,
+        command: 0x02,
+        data: &[],
+    };
+    assert_eq!(
+        p.to_string(),
+        "ADDR: ----\nCMD:  0x02 GET_INFO\nDATA: none\n"
+    );
+    let log = PacketFormatter::new().with_compact(true);
+    assert_eq!(
+        log.display(p).to_string(),
+        "ADDR: ---- CMD: 0x02 GET_INFO DATA: none"
+    );
+    assert_eq!(
+        log.with_names(false).display(p).to_string(),
+        "ADDR: ---- CMD: 0x02 DATA: none"
+    );
+    // a catalog passed in replaces the installed one
+    let mut other = CommandCatalog::new();
+    other.register(None, 0x02, "GET_VERSION");
+    assert_eq!(
+        log.display_with_catalog(p, &other).to_string(),
+        "ADDR: ---- CMD: 0x02 GET_VERSION DATA: none"
+    );
+
+    CommandCatalog::clear_global();
+    assert!(CommandCatalog::global().is_none());
+    assert_eq!(
+        log.display(p).to_string(),
+        "ADDR: ---- CMD: 0x02 DATA: none"
+    );
+}